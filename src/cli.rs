@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use clap::Parser;
+
+use crate::config::optional_env;
+
+/// Watches Factorio server logs and relays joins, leaves, chat and more to a
+/// web dashboard and chat notifiers.
+///
+/// Every setting can be given as an env var or in the config file. The
+/// options below take precedence over both.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// TOML config file, see config.example.toml [env: CONFIG_PATH]
+    #[arg(long, value_name = "PATH")]
+    config: Option<String>,
+
+    /// Comma-separated logs to watch, `-` for stdin [env: FACTORIO_LOG_PATH]
+    #[arg(long, value_name = "PATHS")]
+    log_path: Option<String>,
+
+    /// Address for the web dashboard, e.g. 127.0.0.1:8080 [env: HTTP_BIND_ADDR]
+    #[arg(long, value_name = "ADDR")]
+    bind: Option<String>,
+
+    /// Log notifications instead of sending them [env: DRY_RUN]
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Summarize a log file, or a directory of them, and exit
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<String>,
+
+    /// Run on made-up events instead of a real log
+    #[arg(long)]
+    pub demo: bool,
+
+    /// Send a test message through each notifier, report which failed and exit
+    #[arg(long)]
+    pub test_notifiers: bool,
+}
+
+impl Cli {
+    pub fn config_path(&self) -> Option<String> {
+        self.config.clone().or_else(|| optional_env("CONFIG_PATH"))
+    }
+
+    /// Settings given on the command line, keyed by their env var.
+    pub fn overrides(&self) -> HashMap<&'static str, String> {
+        let mut overrides = HashMap::new();
+        if let Some(log_path) = &self.log_path {
+            overrides.insert("FACTORIO_LOG_PATH", log_path.clone());
+        }
+        if let Some(bind) = &self.bind {
+            overrides.insert("HTTP_BIND_ADDR", bind.clone());
+        }
+        if self.dry_run {
+            overrides.insert("DRY_RUN", "true".to_string());
+        }
+        overrides
+    }
+}
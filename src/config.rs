@@ -0,0 +1,1343 @@
+use std::{collections::HashMap, env, error::Error, fmt, fs, str::FromStr, time::Duration};
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    GameEvent, STDIN_LOG_PATH,
+    cli::Cli,
+    notifier::{DEFAULT_WEBHOOK_TEMPLATE, parse_header, render_body},
+    patterns::{LogPatterns, timezone_from_settings},
+    server_name_from_path,
+    templates::MessageTemplates,
+};
+
+const NON_NEGATIVE_INTEGER: &str = "a non-negative integer";
+
+/// Stands in for secrets in [`Config::sanitized`].
+const REDACTED: &str = "[redacted]";
+
+pub struct TelegramConfig {
+    pub token: String,
+    pub chats: Vec<TelegramChat>,
+    pub max_retries: u32,
+}
+
+pub struct TelegramChat {
+    pub id: String,
+    /// Event kinds this chat is limited to; `None` means everything.
+    pub events: Option<Vec<String>>,
+    /// Topic of a forum supergroup to post in, rather than its General topic.
+    pub thread_id: Option<i64>,
+}
+
+/// Which kinds of events are announced at all.
+#[derive(Clone, Serialize)]
+pub struct NotifyToggles {
+    pub join: bool,
+    pub leave: bool,
+    pub session_reset: bool,
+    /// Covers both the server starting up and its map finishing loading.
+    pub server_start: bool,
+    pub chat: bool,
+    pub death: bool,
+    pub kick: bool,
+    pub ban: bool,
+    pub ban_attempt: bool,
+    pub research: bool,
+    pub achievement: bool,
+    /// Off unless asked for, unlike the rest.
+    pub active: bool,
+    pub empty: bool,
+    pub new_player: bool,
+    pub returning: bool,
+    pub milestone: bool,
+}
+
+impl Default for NotifyToggles {
+    fn default() -> Self {
+        Self {
+            join: true,
+            leave: true,
+            session_reset: true,
+            server_start: true,
+            chat: true,
+            death: true,
+            kick: true,
+            ban: true,
+            ban_attempt: true,
+            research: true,
+            achievement: true,
+            active: false,
+            empty: false,
+            new_player: true,
+            returning: true,
+            milestone: true,
+        }
+    }
+}
+
+impl NotifyToggles {
+    pub fn allows(&self, event: &GameEvent) -> bool {
+        match event {
+            GameEvent::PlayerJoined(_) => self.join,
+            GameEvent::PlayerLeft(_) => self.leave,
+            GameEvent::SessionReset { .. } => self.session_reset,
+            GameEvent::ServerStarting | GameEvent::MapLoaded => self.server_start,
+            GameEvent::ChatMessage { .. } => self.chat,
+            GameEvent::PlayerDied { .. } => self.death,
+            GameEvent::PlayerKicked { .. } => self.kick,
+            GameEvent::PlayerBanned { .. } => self.ban,
+            GameEvent::BannedPlayerAttempt { .. } => self.ban_attempt,
+            GameEvent::ResearchCompleted { .. } => self.research,
+            GameEvent::Achievement { .. } => self.achievement,
+            GameEvent::ServerBecameActive { .. } => self.active,
+            GameEvent::ServerBecameEmpty { .. } => self.empty,
+            GameEvent::NewPlayer(_) => self.new_player,
+            GameEvent::ReturningPlayer { .. } => self.returning,
+            GameEvent::PlaytimeMilestone { .. } => self.milestone,
+            GameEvent::ServerUnresponsive { .. } | GameEvent::ServerRecovered => true,
+            GameEvent::Heartbeat { .. } => true,
+        }
+    }
+}
+
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub token: String,
+    pub room_id: String,
+}
+
+pub struct WebhookConfig {
+    pub url: String,
+    pub template: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// Where the HTTP server listens: `host:port`, IPv6 included as `[::]:8080`,
+/// or `unix:/path/to.sock`.
+pub enum BindAddr {
+    Tcp(String),
+    Unix(String),
+}
+
+impl BindAddr {
+    fn parse(value: String) -> Result<Self, String> {
+        match value.strip_prefix("unix:") {
+            Some("") => Err("HTTP_BIND_ADDR needs a socket path after `unix:`".to_string()),
+            Some(path) => Ok(Self::Unix(path.to_string())),
+            None => Ok(Self::Tcp(value)),
+        }
+    }
+}
+
+impl fmt::Display for BindAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => f.write_str(addr),
+            Self::Unix(path) => write!(f, "unix:{path}"),
+        }
+    }
+}
+
+/// Where player events and offsets are kept, see `STORAGE_BACKEND`.
+#[derive(Clone, Copy)]
+pub enum StorageBackend {
+    /// SQLite at `DB_PATH`, or nothing kept at all without it.
+    Sqlite,
+    /// Nothing kept, even with `DB_PATH` set.
+    Memory,
+}
+
+impl StorageBackend {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "sqlite" => Ok(Self::Sqlite),
+            "memory" => Ok(Self::Memory),
+            _ => Err(format!(
+                "STORAGE_BACKEND must be one of sqlite, memory, got `{value}`"
+            )),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+            Self::Memory => "memory",
+        }
+    }
+}
+
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+pub struct BasicAuthConfig {
+    pub username: String,
+    pub password: String,
+}
+
+pub struct RconConfig {
+    pub address: String,
+    pub password: String,
+    /// How often the roster is checked against RCON, zero for only at startup.
+    pub poll_interval: Duration,
+}
+
+/// Everything the dashboard reads from the environment and config file at startup.
+pub struct Config {
+    pub log_paths: Vec<String>,
+    pub server_names: Vec<String>,
+    pub db_path: Option<String>,
+    pub storage_backend: StorageBackend,
+    /// Buffered events that trigger a database write.
+    pub db_flush_events: usize,
+    /// How often buffered writes are flushed regardless, zero for never.
+    pub db_flush_interval: Duration,
+    /// Sessions shorter than this are left out of playtime totals.
+    pub min_session: Duration,
+    /// Ignore a log line that exactly repeats the previous one.
+    pub skip_repeated_lines: bool,
+    /// Announce a leave even if the player wasn't known to be online.
+    pub notify_unknown_leaves: bool,
+    /// Playtime totals, in hours, announced when a player first joins past
+    /// them. Ascending.
+    pub milestone_hours: Vec<u64>,
+    /// How long a player must have been away to be welcomed back. Zero turns
+    /// it off.
+    pub returning_after: Duration,
+    pub rcon: Option<RconConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub discord_webhook_url: Option<String>,
+    pub slack_webhook_url: Option<String>,
+    pub matrix: Option<MatrixConfig>,
+    pub webhook: Option<WebhookConfig>,
+    /// How long a notifier request may take, zero for no limit.
+    pub http_timeout: Duration,
+    /// Notifiers sent to at the same time, zero for no limit.
+    pub notify_concurrency: usize,
+    pub dry_run: bool,
+    pub http_bind_addr: BindAddr,
+    pub command_secret: Option<String>,
+    pub tls: Option<TlsConfig>,
+    pub basic_auth: Option<BasicAuthConfig>,
+    pub http_access_log: bool,
+    /// Requests logged per minute, zero for no limit.
+    pub http_access_log_per_minute: u32,
+    pub debounce_window: Duration,
+    /// How long a player must stay online before their join is announced.
+    pub join_grace: Duration,
+    pub notify_rate_per_minute: u32,
+    pub join_batch_window: Duration,
+    /// How long after a session reset joins are summed up as reconnects.
+    pub reconnect_grace: Duration,
+    pub notify_ignore_players: Vec<String>,
+    /// Chat is only notified about when it contains one of these.
+    pub chat_notify_keywords: Vec<String>,
+    pub notify_only_players: Vec<String>,
+    pub notify_on: NotifyToggles,
+    pub player_aliases: Vec<(String, String)>,
+    pub event_channel_capacity: usize,
+    pub history_size: usize,
+    pub heartbeat_interval: Duration,
+    pub digest_interval: Duration,
+    pub unresponsive_after: Duration,
+    pub skip_empty_heartbeat: bool,
+    pub templates: MessageTemplates,
+    pub display_timezone: Tz,
+    pub patterns: LogPatterns,
+}
+
+/// Every missing or invalid variable found while reading the environment.
+#[derive(Debug)]
+pub struct ConfigError(Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    /// The effective configuration as JSON, with tokens, passwords and URLs
+    /// that embed credentials replaced by a placeholder.
+    pub fn sanitized(&self) -> Value {
+        let redact = |value: &Option<String>| value.as_ref().map(|_| REDACTED);
+        let mut notifiers = Vec::new();
+        if let Some(telegram) = &self.telegram {
+            notifiers.push(json!({
+                "type": "telegram",
+                "token": REDACTED,
+                "chats": telegram.chats.iter().map(|chat| json!({
+                    "id": chat.id,
+                    "events": chat.events,
+                    "thread_id": chat.thread_id,
+                })).collect::<Vec<_>>(),
+                "max_retries": telegram.max_retries,
+            }));
+        }
+        if self.discord_webhook_url.is_some() {
+            notifiers.push(json!({ "type": "discord", "webhook_url": REDACTED }));
+        }
+        if self.slack_webhook_url.is_some() {
+            notifiers.push(json!({ "type": "slack", "webhook_url": REDACTED }));
+        }
+        if let Some(matrix) = &self.matrix {
+            notifiers.push(json!({
+                "type": "matrix",
+                "homeserver": matrix.homeserver,
+                "token": REDACTED,
+                "room_id": matrix.room_id,
+            }));
+        }
+        if let Some(webhook) = &self.webhook {
+            notifiers.push(json!({
+                "type": "webhook",
+                "url": REDACTED,
+                "template": webhook.template,
+                "headers": webhook.headers.iter().map(|(name, _)| format!("{name}: {REDACTED}")).collect::<Vec<_>>(),
+            }));
+        }
+
+        json!({
+            "servers": self.server_names.iter().zip(&self.log_paths).map(|(name, log_path)| json!({
+                "name": name,
+                "log_path": log_path,
+            })).collect::<Vec<_>>(),
+            "db_path": self.db_path,
+            "storage_backend": self.storage_backend.as_str(),
+            "dry_run": self.dry_run,
+            "http_timeout_secs": self.http_timeout.as_secs(),
+            "notify_concurrency": self.notify_concurrency,
+            "notifiers": notifiers,
+            "rcon": self.rcon.as_ref().map(|rcon| json!({
+                "address": rcon.address,
+                "password": REDACTED,
+                "poll_secs": rcon.poll_interval.as_secs(),
+            })),
+            "http": {
+                "bind_addr": self.http_bind_addr.to_string(),
+                "command_secret": redact(&self.command_secret),
+                "tls": self.tls.is_some(),
+                "username": self.basic_auth.as_ref().map(|auth| &auth.username),
+                "password": self.basic_auth.as_ref().map(|_| REDACTED),
+                "access_log": self.http_access_log,
+                "access_log_per_minute": self.http_access_log_per_minute,
+            },
+            "debounce_secs": self.debounce_window.as_secs(),
+            "join_grace_secs": self.join_grace.as_secs(),
+            "notify_rate_per_minute": self.notify_rate_per_minute,
+            "join_batch_secs": self.join_batch_window.as_secs(),
+            "reconnect_grace_secs": self.reconnect_grace.as_secs(),
+            "skip_repeated_lines": self.skip_repeated_lines,
+            "notify_unknown_leaves": self.notify_unknown_leaves,
+            "playtime_milestones_hours": self.milestone_hours,
+            "returning_player_days": self.returning_after.as_secs() / 86400,
+            "notify_ignore_players": self.notify_ignore_players,
+            "notify_only_players": self.notify_only_players,
+            "chat_notify_keywords": self.chat_notify_keywords,
+            "notify_on": self.notify_on,
+            "player_aliases": self.player_aliases.iter().cloned().collect::<HashMap<_, _>>(),
+            "heartbeat_minutes": self.heartbeat_interval.as_secs() / 60,
+            "skip_empty_heartbeat": self.skip_empty_heartbeat,
+            "digest_interval_minutes": self.digest_interval.as_secs() / 60,
+            "unresponsive_minutes": self.unresponsive_after.as_secs() / 60,
+            "display_timezone": self.display_timezone.name(),
+            "templates": self.templates,
+        })
+    }
+
+    /// Reads the whole configuration, collecting every problem instead of
+    /// stopping at the first one. Command line options take precedence over
+    /// env vars, which take precedence over the file.
+    ///
+    /// In `--demo` mode no log is read, so `FACTORIO_LOG_PATH` only names the
+    /// servers and is optional. Made-up events are never delivered or stored.
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let demo = cli.demo;
+        let mut problems = Vec::new();
+        let settings = match cli
+            .config_path()
+            .as_deref()
+            .map(Settings::from_file)
+            .transpose()
+        {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(problem) => {
+                problems.push(problem);
+                Settings::default()
+            }
+        };
+        let mut settings = settings.with_overrides(cli.overrides());
+        if demo {
+            settings
+                .file
+                .entry("FACTORIO_LOG_PATH")
+                .or_insert_with(|| "demo".to_string());
+        }
+
+        let log_paths = match settings.get("FACTORIO_LOG_PATH") {
+            Some(paths) => split_list(&paths),
+            None => {
+                problems.push("FACTORIO_LOG_PATH env var is required".to_string());
+                Vec::new()
+            }
+        };
+        if log_paths
+            .iter()
+            .filter(|path| *path == STDIN_LOG_PATH)
+            .count()
+            > 1
+        {
+            problems.push("FACTORIO_LOG_PATH may name stdin (-) only once".to_string());
+        }
+        let server_names = match settings.get("SERVER_NAMES") {
+            Some(names) => {
+                let names = split_list(&names);
+                if !log_paths.is_empty() && names.len() != log_paths.len() {
+                    problems.push(
+                        "SERVER_NAMES must list one name per FACTORIO_LOG_PATH entry".to_string(),
+                    );
+                }
+                names
+            }
+            None => log_paths
+                .iter()
+                .map(|path| server_name_from_path(path))
+                .collect(),
+        };
+
+        let rcon = settings.get("RCON_HOST").map(|host| {
+            let port: u16 = settings.parse(&mut problems, "RCON_PORT", 27015, "a port number");
+            RconConfig {
+                address: format!("{}:{}", host, port),
+                password: settings.required(&mut problems, "RCON_PASSWORD"),
+                poll_interval: Duration::from_secs(settings.parse(
+                    &mut problems,
+                    "RCON_POLL_SECS",
+                    0,
+                    NON_NEGATIVE_INTEGER,
+                )),
+            }
+        });
+
+        let telegram_thread_id = settings.get("TELEGRAM_THREAD_ID").and_then(|id| {
+            id.parse()
+                .map_err(|_| problems.push("TELEGRAM_THREAD_ID must be an integer".to_string()))
+                .ok()
+        });
+        let telegram = settings.get("TELEGRAM_TOKEN").map(|token| TelegramConfig {
+            token,
+            chats: split_list(&settings.required(&mut problems, "TELEGRAM_CHAT_ID"))
+                .into_iter()
+                .filter_map(|chat| {
+                    telegram_chat(&chat)
+                        .map_err(|e| problems.push(format!("TELEGRAM_CHAT_ID: {e}")))
+                        .ok()
+                })
+                .map(|chat| TelegramChat {
+                    thread_id: telegram_thread_id,
+                    ..chat
+                })
+                .collect(),
+            max_retries: settings.parse(
+                &mut problems,
+                "TELEGRAM_MAX_RETRIES",
+                3,
+                NON_NEGATIVE_INTEGER,
+            ),
+        });
+        let discord_webhook_url = settings.get("DISCORD_WEBHOOK_URL");
+        let slack_webhook_url = settings.get("SLACK_WEBHOOK_URL");
+        let matrix = settings
+            .get("MATRIX_HOMESERVER")
+            .map(|homeserver| MatrixConfig {
+                homeserver,
+                token: settings.required(&mut problems, "MATRIX_TOKEN"),
+                room_id: settings.required(&mut problems, "MATRIX_ROOM_ID"),
+            });
+        let webhook = settings.get("WEBHOOK_URL").map(|url| {
+            let template = settings
+                .get("WEBHOOK_TEMPLATE")
+                .unwrap_or_else(|| DEFAULT_WEBHOOK_TEMPLATE.to_string());
+            if serde_json::from_str::<serde_json::Value>(&render_body(&template, "", "", ""))
+                .is_err()
+            {
+                problems.push("WEBHOOK_TEMPLATE must be valid JSON".to_string());
+            }
+            let headers = settings
+                .get("WEBHOOK_HEADERS")
+                .map(|headers| split_list(&headers))
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|header| {
+                    webhook_header(&header)
+                        .map_err(|e| problems.push(format!("WEBHOOK_HEADERS: {e}")))
+                        .ok()
+                })
+                .collect();
+            WebhookConfig {
+                url,
+                template,
+                headers,
+            }
+        });
+        let http_timeout = Duration::from_secs(settings.parse(
+            &mut problems,
+            "HTTP_TIMEOUT_SECS",
+            10,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let notify_concurrency =
+            settings.parse(&mut problems, "NOTIFY_CONCURRENCY", 4, NON_NEGATIVE_INTEGER);
+        let dry_run = settings.flag(&mut problems, "DRY_RUN", false) || demo;
+
+        let debounce_window = Duration::from_secs(settings.parse(
+            &mut problems,
+            "DEBOUNCE_SECS",
+            0,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let join_grace = Duration::from_secs(settings.parse(
+            &mut problems,
+            "JOIN_GRACE_SECS",
+            0,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let notify_rate_per_minute = settings.parse(
+            &mut problems,
+            "NOTIFY_RATE_PER_MINUTE",
+            20,
+            NON_NEGATIVE_INTEGER,
+        );
+        let http_access_log = settings.flag(&mut problems, "HTTP_ACCESS_LOG", true);
+        let http_access_log_per_minute = settings.parse(
+            &mut problems,
+            "HTTP_ACCESS_LOG_PER_MINUTE",
+            600,
+            NON_NEGATIVE_INTEGER,
+        );
+        let join_batch_window = Duration::from_secs(settings.parse(
+            &mut problems,
+            "JOIN_BATCH_SECS",
+            0,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let reconnect_grace = Duration::from_secs(settings.parse(
+            &mut problems,
+            "RECONNECT_GRACE_SECS",
+            0,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let player_aliases = settings
+            .get("PLAYER_ALIASES")
+            .map(|aliases| split_list(&aliases))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|pair| match pair.split_once('=') {
+                Some((name, alias)) if !name.trim().is_empty() && !alias.trim().is_empty() => {
+                    Some((name.trim().to_string(), alias.trim().to_string()))
+                }
+                _ => {
+                    problems.push(format!(
+                        "PLAYER_ALIASES: `{pair}` is not in `name=DisplayName` form"
+                    ));
+                    None
+                }
+            })
+            .collect();
+        let notify_on = NotifyToggles {
+            join: settings.flag(&mut problems, "NOTIFY_ON_JOIN", true),
+            leave: settings.flag(&mut problems, "NOTIFY_ON_LEAVE", true),
+            session_reset: settings.flag(&mut problems, "NOTIFY_ON_SESSION_RESET", true),
+            server_start: settings.flag(&mut problems, "NOTIFY_ON_SERVER_START", true),
+            chat: settings.flag(&mut problems, "NOTIFY_ON_CHAT", true),
+            death: settings.flag(&mut problems, "NOTIFY_ON_DEATH", true),
+            kick: settings.flag(&mut problems, "NOTIFY_ON_KICK", true),
+            ban: settings.flag(&mut problems, "NOTIFY_ON_BAN", true),
+            ban_attempt: settings.flag(&mut problems, "NOTIFY_ON_BAN_ATTEMPT", true),
+            research: settings.flag(&mut problems, "NOTIFY_ON_RESEARCH", true),
+            achievement: settings.flag(&mut problems, "NOTIFY_ON_ACHIEVEMENT", true),
+            active: settings.flag(&mut problems, "NOTIFY_ON_ACTIVE", false),
+            empty: settings.flag(&mut problems, "NOTIFY_ON_EMPTY", false),
+            new_player: settings.flag(&mut problems, "NOTIFY_ON_NEW_PLAYER", true),
+            returning: settings.flag(&mut problems, "NOTIFY_ON_RETURNING", true),
+            milestone: settings.flag(&mut problems, "NOTIFY_ON_MILESTONE", true),
+        };
+        let event_channel_capacity = settings.parse(
+            &mut problems,
+            "EVENT_CHANNEL_CAPACITY",
+            100,
+            "a positive integer",
+        );
+        if event_channel_capacity == 0 {
+            problems.push("EVENT_CHANNEL_CAPACITY must be a positive integer".to_string());
+        }
+        let history_size = settings.parse(&mut problems, "HISTORY_SIZE", 100, NON_NEGATIVE_INTEGER);
+        let digest_interval = Duration::from_secs(
+            60 * settings.parse::<u64>(
+                &mut problems,
+                "DIGEST_INTERVAL_MINUTES",
+                0,
+                NON_NEGATIVE_INTEGER,
+            ),
+        );
+        let unresponsive_after = Duration::from_secs(
+            60 * settings.parse::<u64>(
+                &mut problems,
+                "UNRESPONSIVE_MINUTES",
+                0,
+                NON_NEGATIVE_INTEGER,
+            ),
+        );
+        let heartbeat_interval = Duration::from_secs(
+            60 * settings.parse::<u64>(&mut problems, "HEARTBEAT_MINUTES", 0, NON_NEGATIVE_INTEGER),
+        );
+        let db_flush_events =
+            settings.parse(&mut problems, "DB_FLUSH_EVENTS", 100, NON_NEGATIVE_INTEGER);
+        let db_flush_interval = Duration::from_secs(settings.parse(
+            &mut problems,
+            "DB_FLUSH_SECS",
+            5,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let min_session = Duration::from_secs(settings.parse(
+            &mut problems,
+            "MIN_SESSION_SECONDS",
+            0,
+            NON_NEGATIVE_INTEGER,
+        ));
+        let mut milestone_hours: Vec<u64> = split_list(
+            &settings
+                .get("PLAYTIME_MILESTONES_HOURS")
+                .unwrap_or_else(|| "10,50,100".to_string()),
+        )
+        .iter()
+        .filter_map(|hours| match hours.parse() {
+            Ok(0) | Err(_) => {
+                problems.push(format!(
+                    "PLAYTIME_MILESTONES_HOURS must list positive integers, got `{hours}`"
+                ));
+                None
+            }
+            Ok(hours) => Some(hours),
+        })
+        .collect();
+        milestone_hours.sort_unstable();
+        milestone_hours.dedup();
+        let returning_after = Duration::from_secs(
+            86400
+                * settings.parse::<u64>(
+                    &mut problems,
+                    "RETURNING_PLAYER_DAYS",
+                    0,
+                    NON_NEGATIVE_INTEGER,
+                ),
+        );
+        let storage_backend = StorageBackend::parse(
+            &settings
+                .get("STORAGE_BACKEND")
+                .unwrap_or_else(|| "sqlite".to_string()),
+        )
+        .unwrap_or_else(|e| {
+            problems.push(e);
+            StorageBackend::Sqlite
+        });
+        let skip_repeated_lines = settings.flag(&mut problems, "SKIP_REPEATED_LINES", false);
+        let notify_unknown_leaves = settings.flag(&mut problems, "NOTIFY_UNKNOWN_LEAVES", false);
+        let skip_empty_heartbeat = settings.flag(&mut problems, "SKIP_EMPTY_HEARTBEAT", false);
+        let tls = match (settings.get("TLS_CERT_PATH"), settings.get("TLS_KEY_PATH")) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+            }),
+            (None, None) => None,
+            _ => {
+                problems.push("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string());
+                None
+            }
+        };
+        let http_bind_addr = BindAddr::parse(
+            settings
+                .get("HTTP_BIND_ADDR")
+                .unwrap_or_else(|| "0.0.0.0:8080".to_string()),
+        )
+        .unwrap_or_else(|e| {
+            problems.push(e);
+            BindAddr::Tcp(String::new())
+        });
+        if matches!(http_bind_addr, BindAddr::Unix(_)) && tls.is_some() {
+            problems.push("TLS can't be used with a Unix socket HTTP_BIND_ADDR".to_string());
+        }
+        let basic_auth = match (settings.get("HTTP_USERNAME"), settings.get("HTTP_PASSWORD")) {
+            (Some(username), Some(password)) => Some(BasicAuthConfig { username, password }),
+            (None, None) => None,
+            _ => {
+                problems.push("HTTP_USERNAME and HTTP_PASSWORD must be set together".to_string());
+                None
+            }
+        };
+        let display_timezone = timezone_from_settings(&settings, "DISPLAY_TIMEZONE")
+            .unwrap_or_else(|problem| {
+                problems.push(problem);
+                Tz::UTC
+            });
+        let patterns = LogPatterns::load(&settings)
+            .map_err(|errors| problems.extend(errors))
+            .ok();
+
+        match patterns {
+            Some(patterns) if problems.is_empty() => Ok(Self {
+                log_paths,
+                server_names,
+                db_path: settings.get("DB_PATH").filter(|_| !demo),
+                storage_backend,
+                db_flush_events,
+                db_flush_interval,
+                min_session,
+                skip_repeated_lines,
+                notify_unknown_leaves,
+                milestone_hours,
+                returning_after,
+                rcon,
+                telegram,
+                discord_webhook_url,
+                slack_webhook_url,
+                matrix,
+                webhook,
+                http_timeout,
+                notify_concurrency,
+                dry_run,
+                http_bind_addr,
+                command_secret: settings.get("COMMAND_SECRET"),
+                tls,
+                basic_auth,
+                http_access_log,
+                http_access_log_per_minute,
+                debounce_window,
+                join_grace,
+                notify_rate_per_minute,
+                join_batch_window,
+                reconnect_grace,
+                notify_ignore_players: settings
+                    .get("NOTIFY_IGNORE_PLAYERS")
+                    .map(|names| split_list(&names))
+                    .unwrap_or_default(),
+                chat_notify_keywords: settings
+                    .get("CHAT_NOTIFY_KEYWORDS")
+                    .map(|keywords| split_list(&keywords))
+                    .unwrap_or_default(),
+                notify_only_players: settings
+                    .get("NOTIFY_ONLY_PLAYERS")
+                    .map(|names| split_list(&names))
+                    .unwrap_or_default(),
+                notify_on,
+                player_aliases,
+                event_channel_capacity,
+                history_size,
+                heartbeat_interval,
+                digest_interval,
+                unresponsive_after,
+                skip_empty_heartbeat,
+                templates: MessageTemplates::load(&settings),
+                display_timezone,
+                patterns,
+            }),
+            _ => Err(ConfigError(problems)),
+        }
+    }
+}
+
+/// Reads an env var, treating an empty value the same as an unset one.
+pub fn optional_env(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// Looks settings up by their env var name, falling back to the config file.
+/// Command line options override both.
+#[derive(Default)]
+pub struct Settings {
+    cli: HashMap<&'static str, String>,
+    file: HashMap<&'static str, String>,
+}
+
+impl Settings {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+        let file: FileConfig =
+            toml::from_str(&contents).map_err(|e| format!("Failed to parse {path}: {e}"))?;
+        Ok(Self {
+            cli: HashMap::new(),
+            file: file.into_settings(),
+        })
+    }
+
+    fn with_overrides(self, cli: HashMap<&'static str, String>) -> Self {
+        Self { cli, ..self }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.cli
+            .get(key)
+            .cloned()
+            .or_else(|| optional_env(key))
+            .or_else(|| self.file.get(key).cloned())
+    }
+
+    fn required(&self, problems: &mut Vec<String>, key: &str) -> String {
+        self.get(key).unwrap_or_else(|| {
+            problems.push(format!("{key} env var is required"));
+            String::new()
+        })
+    }
+
+    /// Accepts `true`/`false`, `1`/`0` and `yes`/`no`.
+    fn flag(&self, problems: &mut Vec<String>, key: &str, default: bool) -> bool {
+        match self.get(key).map(|value| value.to_lowercase()).as_deref() {
+            Some("true" | "1" | "yes") => true,
+            Some("false" | "0" | "no") => false,
+            None => default,
+            Some(_) => {
+                problems.push(format!("{key} must be true or false"));
+                default
+            }
+        }
+    }
+
+    fn parse<T: FromStr>(
+        &self,
+        problems: &mut Vec<String>,
+        key: &str,
+        default: T,
+        expected: &str,
+    ) -> T {
+        match self.get(key) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                problems.push(format!("{key} must be {expected}"));
+                default
+            }),
+            None => default,
+        }
+    }
+}
+
+/// Layout of `config.toml`. Every field mirrors an env var of the same meaning.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    servers: Vec<FileServer>,
+    db_path: Option<String>,
+    storage_backend: Option<String>,
+    db_flush_events: Option<usize>,
+    db_flush_secs: Option<u64>,
+    min_session_seconds: Option<u64>,
+    playtime_milestones_hours: Option<Vec<u64>>,
+    returning_player_days: Option<u64>,
+    skip_repeated_lines: Option<bool>,
+    notify_unknown_leaves: Option<bool>,
+    http_timeout_secs: Option<u64>,
+    notify_concurrency: Option<usize>,
+    dry_run: Option<bool>,
+    debounce_secs: Option<u64>,
+    join_grace_secs: Option<u64>,
+    notify_rate_per_minute: Option<u32>,
+    join_batch_secs: Option<u64>,
+    reconnect_grace_secs: Option<u64>,
+    notify_ignore_players: Vec<String>,
+    chat_notify_keywords: Vec<String>,
+    notify_only_players: Vec<String>,
+    notify_on: FileNotifyOn,
+    player_aliases: HashMap<String, String>,
+    event_channel_capacity: Option<usize>,
+    history_size: Option<usize>,
+    heartbeat_minutes: Option<u64>,
+    skip_empty_heartbeat: Option<bool>,
+    digest_interval_minutes: Option<u64>,
+    unresponsive_minutes: Option<u64>,
+    display_timezone: Option<String>,
+    log_timezone: Option<String>,
+    http: FileHttp,
+    rcon: FileRcon,
+    telegram: FileTelegram,
+    discord: FileDiscord,
+    slack: FileSlack,
+    matrix: FileMatrix,
+    webhook: FileWebhook,
+    templates: FileTemplates,
+    emoji: FileEmoji,
+    patterns: FilePatterns,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileNotifyOn {
+    join: Option<bool>,
+    leave: Option<bool>,
+    session_reset: Option<bool>,
+    server_start: Option<bool>,
+    chat: Option<bool>,
+    death: Option<bool>,
+    kick: Option<bool>,
+    ban: Option<bool>,
+    ban_attempt: Option<bool>,
+    research: Option<bool>,
+    achievement: Option<bool>,
+    active: Option<bool>,
+    empty: Option<bool>,
+    new_player: Option<bool>,
+    returning: Option<bool>,
+    milestone: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileServer {
+    log_path: String,
+    name: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileHttp {
+    bind_addr: Option<String>,
+    command_secret: Option<String>,
+    tls_cert_path: Option<String>,
+    tls_key_path: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    access_log: Option<bool>,
+    access_log_per_minute: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRcon {
+    host: Option<String>,
+    port: Option<u16>,
+    password: Option<String>,
+    poll_secs: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileTelegram {
+    token: Option<String>,
+    chat_id: Option<String>,
+    thread_id: Option<i64>,
+    max_retries: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileDiscord {
+    webhook_url: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSlack {
+    webhook_url: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileMatrix {
+    homeserver: Option<String>,
+    token: Option<String>,
+    room_id: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileWebhook {
+    url: Option<String>,
+    template: Option<String>,
+    headers: Vec<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileTemplates {
+    language: Option<String>,
+    join: Option<String>,
+    leave: Option<String>,
+    session_reset: Option<String>,
+    heartbeat: Option<String>,
+    server_active: Option<String>,
+    server_empty: Option<String>,
+    join_many: Option<String>,
+    reconnected: Option<String>,
+    list: Option<String>,
+    previous_session: Option<String>,
+    server_starting: Option<String>,
+    map_loaded: Option<String>,
+    chat: Option<String>,
+    death: Option<String>,
+    death_cause: Option<String>,
+    kick: Option<String>,
+    kick_reason: Option<String>,
+    ban: Option<String>,
+    ban_reason: Option<String>,
+    ban_attempt: Option<String>,
+    research: Option<String>,
+    achievement: Option<String>,
+    unresponsive: Option<String>,
+    recovered: Option<String>,
+    new_player: Option<String>,
+    returning: Option<String>,
+    milestone: Option<String>,
+    digest: Option<String>,
+    digest_joined: Option<String>,
+    digest_left: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileEmoji {
+    join: Option<String>,
+    leave: Option<String>,
+    reset: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilePatterns {
+    preset: Option<String>,
+    death: Option<String>,
+    kick: Option<String>,
+    ban: Option<String>,
+    ban_attempt: Option<String>,
+    research: Option<String>,
+    achievement: Option<String>,
+    starting: Option<String>,
+    map_loaded: Option<String>,
+    session_reset_marker: Option<String>,
+}
+
+impl FileConfig {
+    fn into_settings(self) -> HashMap<&'static str, String> {
+        let mut settings = HashMap::new();
+        let mut set = |key, value: Option<String>| {
+            if let Some(value) = value {
+                settings.insert(key, value);
+            }
+        };
+
+        if !self.servers.is_empty() {
+            let (log_paths, names): (Vec<_>, Vec<_>) = self
+                .servers
+                .into_iter()
+                .map(|server| {
+                    let name = server
+                        .name
+                        .unwrap_or_else(|| server_name_from_path(&server.log_path));
+                    (server.log_path, name)
+                })
+                .unzip();
+            set("FACTORIO_LOG_PATH", Some(log_paths.join(",")));
+            set("SERVER_NAMES", Some(names.join(",")));
+        }
+        set("DB_PATH", self.db_path);
+        set("STORAGE_BACKEND", self.storage_backend);
+        set(
+            "DB_FLUSH_EVENTS",
+            self.db_flush_events.map(|v| v.to_string()),
+        );
+        set("DB_FLUSH_SECS", self.db_flush_secs.map(|v| v.to_string()));
+        set(
+            "MIN_SESSION_SECONDS",
+            self.min_session_seconds.map(|v| v.to_string()),
+        );
+        set(
+            "SKIP_REPEATED_LINES",
+            self.skip_repeated_lines.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_UNKNOWN_LEAVES",
+            self.notify_unknown_leaves.map(|v| v.to_string()),
+        );
+        set(
+            "PLAYTIME_MILESTONES_HOURS",
+            self.playtime_milestones_hours.map(|hours| {
+                hours
+                    .iter()
+                    .map(u64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        set(
+            "RETURNING_PLAYER_DAYS",
+            self.returning_player_days.map(|v| v.to_string()),
+        );
+        set(
+            "HTTP_TIMEOUT_SECS",
+            self.http_timeout_secs.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_CONCURRENCY",
+            self.notify_concurrency.map(|v| v.to_string()),
+        );
+        set("DRY_RUN", self.dry_run.map(|v| v.to_string()));
+        set("DEBOUNCE_SECS", self.debounce_secs.map(|v| v.to_string()));
+        set(
+            "JOIN_GRACE_SECS",
+            self.join_grace_secs.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_RATE_PER_MINUTE",
+            self.notify_rate_per_minute.map(|v| v.to_string()),
+        );
+        set(
+            "JOIN_BATCH_SECS",
+            self.join_batch_secs.map(|v| v.to_string()),
+        );
+        set(
+            "RECONNECT_GRACE_SECS",
+            self.reconnect_grace_secs.map(|v| v.to_string()),
+        );
+        if !self.notify_ignore_players.is_empty() {
+            set(
+                "NOTIFY_IGNORE_PLAYERS",
+                Some(self.notify_ignore_players.join(",")),
+            );
+        }
+        if !self.chat_notify_keywords.is_empty() {
+            set(
+                "CHAT_NOTIFY_KEYWORDS",
+                Some(self.chat_notify_keywords.join(",")),
+            );
+        }
+        if !self.notify_only_players.is_empty() {
+            set(
+                "NOTIFY_ONLY_PLAYERS",
+                Some(self.notify_only_players.join(",")),
+            );
+        }
+        if !self.player_aliases.is_empty() {
+            let aliases: Vec<String> = self
+                .player_aliases
+                .iter()
+                .map(|(name, alias)| format!("{name}={alias}"))
+                .collect();
+            set("PLAYER_ALIASES", Some(aliases.join(",")));
+        }
+        set("NOTIFY_ON_JOIN", self.notify_on.join.map(|v| v.to_string()));
+        set(
+            "NOTIFY_ON_LEAVE",
+            self.notify_on.leave.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_SESSION_RESET",
+            self.notify_on.session_reset.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_SERVER_START",
+            self.notify_on.server_start.map(|v| v.to_string()),
+        );
+        set("NOTIFY_ON_CHAT", self.notify_on.chat.map(|v| v.to_string()));
+        set(
+            "NOTIFY_ON_DEATH",
+            self.notify_on.death.map(|v| v.to_string()),
+        );
+        set("NOTIFY_ON_KICK", self.notify_on.kick.map(|v| v.to_string()));
+        set("NOTIFY_ON_BAN", self.notify_on.ban.map(|v| v.to_string()));
+        set(
+            "NOTIFY_ON_BAN_ATTEMPT",
+            self.notify_on.ban_attempt.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_RESEARCH",
+            self.notify_on.research.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_ACHIEVEMENT",
+            self.notify_on.achievement.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_ACTIVE",
+            self.notify_on.active.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_EMPTY",
+            self.notify_on.empty.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_NEW_PLAYER",
+            self.notify_on.new_player.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_RETURNING",
+            self.notify_on.returning.map(|v| v.to_string()),
+        );
+        set(
+            "NOTIFY_ON_MILESTONE",
+            self.notify_on.milestone.map(|v| v.to_string()),
+        );
+        set(
+            "EVENT_CHANNEL_CAPACITY",
+            self.event_channel_capacity.map(|v| v.to_string()),
+        );
+        set("HISTORY_SIZE", self.history_size.map(|v| v.to_string()));
+        set(
+            "HEARTBEAT_MINUTES",
+            self.heartbeat_minutes.map(|v| v.to_string()),
+        );
+        set(
+            "SKIP_EMPTY_HEARTBEAT",
+            self.skip_empty_heartbeat.map(|v| v.to_string()),
+        );
+        set(
+            "DIGEST_INTERVAL_MINUTES",
+            self.digest_interval_minutes.map(|v| v.to_string()),
+        );
+        set(
+            "UNRESPONSIVE_MINUTES",
+            self.unresponsive_minutes.map(|v| v.to_string()),
+        );
+        set("DISPLAY_TIMEZONE", self.display_timezone);
+        set("LOG_TIMEZONE", self.log_timezone);
+        set("HTTP_BIND_ADDR", self.http.bind_addr);
+        set("COMMAND_SECRET", self.http.command_secret);
+        set("TLS_CERT_PATH", self.http.tls_cert_path);
+        set("TLS_KEY_PATH", self.http.tls_key_path);
+        set("HTTP_USERNAME", self.http.username);
+        set("HTTP_PASSWORD", self.http.password);
+        set(
+            "HTTP_ACCESS_LOG",
+            self.http.access_log.map(|v| v.to_string()),
+        );
+        set(
+            "HTTP_ACCESS_LOG_PER_MINUTE",
+            self.http.access_log_per_minute.map(|v| v.to_string()),
+        );
+        set("RCON_HOST", self.rcon.host);
+        set("RCON_PORT", self.rcon.port.map(|v| v.to_string()));
+        set("RCON_PASSWORD", self.rcon.password);
+        set("RCON_POLL_SECS", self.rcon.poll_secs.map(|v| v.to_string()));
+        set("TELEGRAM_TOKEN", self.telegram.token);
+        set("TELEGRAM_CHAT_ID", self.telegram.chat_id);
+        set(
+            "TELEGRAM_THREAD_ID",
+            self.telegram.thread_id.map(|v| v.to_string()),
+        );
+        set(
+            "TELEGRAM_MAX_RETRIES",
+            self.telegram.max_retries.map(|v| v.to_string()),
+        );
+        set("DISCORD_WEBHOOK_URL", self.discord.webhook_url);
+        set("SLACK_WEBHOOK_URL", self.slack.webhook_url);
+        set("MATRIX_HOMESERVER", self.matrix.homeserver);
+        set("MATRIX_TOKEN", self.matrix.token);
+        set("MATRIX_ROOM_ID", self.matrix.room_id);
+        set("WEBHOOK_URL", self.webhook.url);
+        set("WEBHOOK_TEMPLATE", self.webhook.template);
+        if !self.webhook.headers.is_empty() {
+            set("WEBHOOK_HEADERS", Some(self.webhook.headers.join(",")));
+        }
+        set("MSG_LANGUAGE", self.templates.language);
+        set("MSG_JOIN", self.templates.join);
+        set("MSG_LEAVE", self.templates.leave);
+        set("MSG_SESSION_RESET", self.templates.session_reset);
+        set("MSG_HEARTBEAT", self.templates.heartbeat);
+        set("MSG_SERVER_ACTIVE", self.templates.server_active);
+        set("MSG_SERVER_EMPTY", self.templates.server_empty);
+        set("MSG_JOIN_MANY", self.templates.join_many);
+        set("MSG_RECONNECTED", self.templates.reconnected);
+        set("MSG_LIST", self.templates.list);
+        set("MSG_PREVIOUS_SESSION", self.templates.previous_session);
+        set("MSG_SERVER_STARTING", self.templates.server_starting);
+        set("MSG_MAP_LOADED", self.templates.map_loaded);
+        set("MSG_CHAT", self.templates.chat);
+        set("MSG_DEATH", self.templates.death);
+        set("MSG_DEATH_CAUSE", self.templates.death_cause);
+        set("MSG_KICK", self.templates.kick);
+        set("MSG_KICK_REASON", self.templates.kick_reason);
+        set("MSG_BAN", self.templates.ban);
+        set("MSG_BAN_REASON", self.templates.ban_reason);
+        set("MSG_BAN_ATTEMPT", self.templates.ban_attempt);
+        set("MSG_RESEARCH", self.templates.research);
+        set("MSG_ACHIEVEMENT", self.templates.achievement);
+        set("MSG_UNRESPONSIVE", self.templates.unresponsive);
+        set("MSG_RECOVERED", self.templates.recovered);
+        set("MSG_NEW_PLAYER", self.templates.new_player);
+        set("MSG_RETURNING", self.templates.returning);
+        set("MSG_MILESTONE", self.templates.milestone);
+        set("MSG_DIGEST", self.templates.digest);
+        set("MSG_DIGEST_JOINED", self.templates.digest_joined);
+        set("MSG_DIGEST_LEFT", self.templates.digest_left);
+        set("EMOJI_JOIN", self.emoji.join);
+        set("EMOJI_LEAVE", self.emoji.leave);
+        set("EMOJI_RESET", self.emoji.reset);
+        set("DEATH_PATTERN", self.patterns.death);
+        set("KICK_PATTERN", self.patterns.kick);
+        set("BAN_PATTERN", self.patterns.ban);
+        set("BAN_ATTEMPT_PATTERN", self.patterns.ban_attempt);
+        set("RESEARCH_PATTERN", self.patterns.research);
+        set("ACHIEVEMENT_PATTERN", self.patterns.achievement);
+        set("STARTING_PATTERN", self.patterns.starting);
+        set("MAP_LOADED_PATTERN", self.patterns.map_loaded);
+        set("LOG_FORMAT_PRESET", self.patterns.preset);
+        set("SESSION_RESET_MARKER", self.patterns.session_reset_marker);
+
+        settings
+    }
+}
+
+/// Splits a `Name: value` header, checking both halves are usable.
+fn webhook_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("`{header}` is not in `Name: value` form"))?;
+    let (name, value) = (name.trim(), value.trim());
+    parse_header(name, value)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Parses a `chat_id` entry, optionally followed by `:kind|kind` to limit
+/// which events reach that chat.
+fn telegram_chat(entry: &str) -> Result<TelegramChat, String> {
+    let Some((id, events)) = entry.split_once(':') else {
+        return Ok(TelegramChat {
+            id: entry.to_string(),
+            events: None,
+            thread_id: None,
+        });
+    };
+    let events: Vec<String> = events
+        .split('|')
+        .map(|kind| kind.trim().to_lowercase())
+        .filter(|kind| !kind.is_empty())
+        .collect();
+    if let Some(unknown) = events
+        .iter()
+        .find(|kind| !GameEvent::KINDS.contains(&kind.as_str()))
+    {
+        return Err(format!(
+            "unknown event kind `{unknown}`, expected one of: {}",
+            GameEvent::KINDS.join(", ")
+        ));
+    }
+    Ok(TelegramChat {
+        id: id.trim().to_string(),
+        events: Some(events),
+        thread_id: None,
+    })
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{sync::broadcast::Sender, time::sleep};
+use tracing::info;
+
+use crate::ServerEvent;
+
+/// Holds join/leave events back for a short window so that a player who
+/// flaps (leaves and rejoins, or vice versa) produces no events at all.
+pub struct Debouncer {
+    window: Duration,
+    // (server, player) -> id of the event currently waiting out the window
+    pending: Arc<Mutex<HashMap<(String, String), u64>>>,
+    next_id: AtomicU64,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Sends `event` once the window passes, unless the opposite event for the
+    /// same player arrives first, in which case both are dropped.
+    pub fn dispatch(&self, tx: &Sender<ServerEvent>, player: &str, event: ServerEvent) {
+        if self.window.is_zero() {
+            let _ = tx.send(event);
+            return;
+        }
+
+        let key = (event.server.clone(), player.to_string());
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut pending = self.pending.lock().unwrap();
+            // Join and leave strictly alternate per player, so anything still
+            // pending here is the opposite event
+            if pending.remove(&key).is_some() {
+                info!(player, "Suppressed flapping notifications");
+                return;
+            }
+            pending.insert(key.clone(), id);
+        }
+
+        let pending = Arc::clone(&self.pending);
+        let window = self.window;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            sleep(window).await;
+
+            let still_pending = {
+                let mut pending = pending.lock().unwrap();
+                if pending.get(&key) == Some(&id) {
+                    pending.remove(&key);
+                    true
+                } else {
+                    false
+                }
+            };
+            if still_pending {
+                let _ = tx.send(event);
+            }
+        });
+    }
+}
+
+/// Holds join events back, along with the welcomes and such they set off,
+/// until the player has been online for `grace`, so someone who disconnects
+/// straight away is announced neither joining nor leaving.
+pub struct JoinGrace {
+    grace: Duration,
+    // (server, player) -> id of the join currently waiting out the grace period
+    pending: Arc<Mutex<HashMap<(String, String), u64>>>,
+    next_id: AtomicU64,
+}
+
+impl JoinGrace {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.grace.is_zero()
+    }
+
+    /// Sends `events`, the join and whatever it set off, once the grace period
+    /// passes, unless [`JoinGrace::cancel`] is called for the player first.
+    pub fn delay(
+        &self,
+        tx: &Sender<ServerEvent>,
+        server: &str,
+        player: &str,
+        events: Vec<ServerEvent>,
+    ) {
+        let key = (server.to_string(), player.to_string());
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pending.lock().unwrap().insert(key.clone(), id);
+
+        let pending = Arc::clone(&self.pending);
+        let grace = self.grace;
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            sleep(grace).await;
+
+            let still_online = {
+                let mut pending = pending.lock().unwrap();
+                if pending.get(&key) == Some(&id) {
+                    pending.remove(&key);
+                    true
+                } else {
+                    false
+                }
+            };
+            if still_online {
+                for event in events {
+                    let _ = tx.send(event);
+                }
+            }
+        });
+    }
+
+    /// Drops the join still waiting for `player` on `server`, returning
+    /// whether there was one.
+    pub fn cancel(&self, server: &str, player: &str) -> bool {
+        let key = (server.to_string(), player.to_string());
+        let cancelled = self.pending.lock().unwrap().remove(&key).is_some();
+        if cancelled {
+            info!(player, "Player left within the join grace period");
+        }
+        cancelled
+    }
+}
@@ -0,0 +1,69 @@
+use std::{sync::Arc, time::Duration};
+
+use tracing::info;
+
+use crate::{AppState, ServerState, handle_line};
+
+const PLAYERS: &[&str] = &["Alice", "Bob", "Charlie", "Dana", "Eve", "Frank", "Grace"];
+const CHAT: &[&str] = &[
+    "gg",
+    "need more iron plates",
+    "who put the belt through the mall?",
+    "brb",
+    "biters at the north wall!",
+    "rocket silo is up",
+];
+const LINE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Feeds made-up log lines through the normal pipeline instead of reading a
+/// real log, so the dashboard can be tried without a Factorio server.
+pub async fn run(app_state: Arc<AppState>, server: Arc<ServerState>) {
+    let mut shutdown = app_state.shutdown.subscribe();
+    let mut ticker = tokio::time::interval(LINE_INTERVAL);
+    let mut online = Vec::new();
+    let mut tick = 0;
+
+    info!(server = %server.name, "Demo mode, generating log lines");
+    server
+        .watcher_alive
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    handle_line(&app_state, &server, app_state.patterns.session_marker()).await;
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.recv() => break,
+        }
+        tick += LINE_INTERVAL.as_secs() * 60;
+        let line = next_line(&mut online, tick, app_state.patterns.session_marker());
+        handle_line(&app_state, &server, &line).await;
+    }
+}
+
+/// Picks the next event: mostly joins and leaves, some chat and now and then
+/// a session reset.
+fn next_line(online: &mut Vec<&'static str>, tick: u64, session_marker: &str) -> String {
+    let roll = fastrand::u8(0..100);
+    if roll < 3 {
+        online.clear();
+        return session_marker.to_string();
+    }
+
+    let offline: Vec<&'static str> = PLAYERS
+        .iter()
+        .copied()
+        .filter(|player| !online.contains(player))
+        .collect();
+    if online.is_empty() || (roll < 45 && !offline.is_empty()) {
+        let player = offline[fastrand::usize(..offline.len())];
+        online.push(player);
+        return format!("JOIN|{tick}|{player}");
+    }
+    if roll < 75 {
+        let player = online.swap_remove(fastrand::usize(..online.len()));
+        return format!("LEAVE|{tick}|{player}");
+    }
+    let player = online[fastrand::usize(..online.len())];
+    let text = CHAT[fastrand::usize(..CHAT.len())];
+    format!("[CHAT] {player}: {text}")
+}
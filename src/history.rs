@@ -0,0 +1,90 @@
+use std::{collections::VecDeque, sync::RwLock};
+
+use crate::ServerEvent;
+
+/// The most recent events, oldest first, so clients can catch up on load.
+/// Holds at most `capacity` events: once full, each new event evicts the
+/// oldest one. Readers only take a shared lock, so HTTP clients fetching the
+/// history don't hold each other up, and the watcher only waits for them
+/// while they copy the buffer.
+pub struct EventHistory {
+    capacity: usize,
+    events: RwLock<VecDeque<ServerEvent>>,
+}
+
+impl EventHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: RwLock::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Appends an event, evicting the oldest once the buffer is full.
+    pub fn push(&self, event: ServerEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut events = self.events.write().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    pub fn snapshot(&self) -> Vec<ServerEvent> {
+        self.events.read().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::GameEvent;
+
+    fn event(n: usize) -> ServerEvent {
+        ServerEvent {
+            server: "test".to_string(),
+            at: Utc::now(),
+            online: n,
+            event: GameEvent::PlayerJoined(format!("Player{n}")),
+        }
+    }
+
+    #[test]
+    fn never_exceeds_capacity_under_concurrent_pushes() {
+        let history = Arc::new(EventHistory::new(10));
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let history = Arc::clone(&history);
+                thread::spawn(move || {
+                    for n in 0..1000 {
+                        history.push(event(writer * 1000 + n));
+                        assert!(history.snapshot().len() <= 10);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(history.snapshot().len(), 10);
+    }
+
+    #[test]
+    fn drops_the_oldest_event_first() {
+        let history = EventHistory::new(2);
+        for n in 0..3 {
+            history.push(event(n));
+        }
+
+        let online: Vec<usize> = history.snapshot().iter().map(|e| e.online).collect();
+        assert_eq!(online, [1, 2]);
+    }
+}
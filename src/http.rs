@@ -0,0 +1,978 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Json, Router,
+    extract::{
+        Path as UrlPath, Query, Request, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{
+        Html, IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast::error::RecvError, mpsc},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    AppState, GameEvent, ServerEvent,
+    config::{BasicAuthConfig, BindAddr, TlsConfig},
+    patterns::ParsedLine,
+    rate_limit::RateLimiter,
+};
+
+#[derive(Serialize)]
+struct PlayersResponse {
+    players: Vec<String>,
+    count: usize,
+    server_started_at: DateTime<Utc>,
+    servers: Vec<ServerPlayers>,
+}
+
+#[derive(Serialize)]
+struct ServerPlayers {
+    server: String,
+    /// In the order they joined.
+    players: Vec<String>,
+    count: usize,
+    joined_at: HashMap<String, DateTime<Utc>>,
+    /// How long each player had been online when this was taken, so clients
+    /// can count on from there without trusting their own clock.
+    online_secs: HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+struct WhoamiQuery {
+    line: String,
+}
+
+/// How a single log line would be read, for trying out custom patterns.
+#[derive(Serialize)]
+struct WhoamiResponse {
+    /// The `LOG_FORMAT_PRESET` in use.
+    format: String,
+    at: Option<DateTime<Utc>>,
+    /// The line with its timestamp split off, as the patterns see it.
+    content: String,
+    event: ParsedLine,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    servers: Vec<ServerHealth>,
+}
+
+#[derive(Serialize)]
+struct ServerHealth {
+    server: String,
+    log_file_exists: bool,
+    last_line_at: Option<DateTime<Utc>>,
+    watcher_alive: bool,
+    peak_players: usize,
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    players_online: usize,
+    unique_players: usize,
+    session_joins: usize,
+    session_leaves: usize,
+    servers: Vec<ServerStats>,
+}
+
+#[derive(Serialize)]
+struct ServerStats {
+    server: String,
+    players_online: usize,
+    peak_players: usize,
+    unique_players: usize,
+    session_joins: usize,
+    session_leaves: usize,
+    /// Time since the last session reset, when the dashboard knows it.
+    uptime_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CommandRequest {
+    command: String,
+    server: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CommandResponse {
+    server: String,
+    response: String,
+}
+
+#[derive(Serialize)]
+struct PlaytimeEntry {
+    player: String,
+    seconds: u64,
+}
+
+#[derive(Serialize)]
+struct PlayerPlaytimeResponse {
+    player: String,
+    seconds: u64,
+    last_seen: DateTime<Utc>,
+    online: bool,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsMessage {
+    Snapshot {
+        players: Vec<String>,
+        servers: Vec<ServerPlayers>,
+    },
+    Join {
+        server: String,
+        player: String,
+    },
+    Leave {
+        server: String,
+        player: String,
+    },
+    SessionReset {
+        server: String,
+        previous_session_secs: Option<u64>,
+    },
+    Starting {
+        server: String,
+    },
+    MapLoaded {
+        server: String,
+    },
+    Chat {
+        server: String,
+        author: String,
+        text: String,
+    },
+    Death {
+        server: String,
+        player: String,
+        cause: Option<String>,
+    },
+    Kick {
+        server: String,
+        player: String,
+        reason: Option<String>,
+    },
+    Ban {
+        server: String,
+        player: String,
+        reason: Option<String>,
+    },
+    BanAttempt {
+        server: String,
+        player: String,
+    },
+    Research {
+        server: String,
+        technology: String,
+    },
+    Achievement {
+        server: String,
+        player: String,
+        achievement: String,
+    },
+    Unresponsive {
+        server: String,
+        silent_secs: u64,
+    },
+    Recovered {
+        server: String,
+    },
+    Active {
+        server: String,
+        player: String,
+    },
+    Empty {
+        server: String,
+        player: String,
+    },
+    NewPlayer {
+        server: String,
+        player: String,
+    },
+    ReturningPlayer {
+        server: String,
+        player: String,
+        away_secs: u64,
+    },
+    Milestone {
+        server: String,
+        player: String,
+        hours: u64,
+    },
+    Heartbeat {
+        server: String,
+        players: usize,
+        peak: usize,
+        uptime_secs: u64,
+    },
+}
+
+/// A game event as streamed to clients, stamped with when it happened.
+#[derive(Serialize)]
+struct WsEvent {
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    message: WsMessage,
+}
+
+impl From<ServerEvent> for WsEvent {
+    fn from(
+        ServerEvent {
+            server, at, event, ..
+        }: ServerEvent,
+    ) -> Self {
+        let message = match event {
+            GameEvent::PlayerJoined(player) => WsMessage::Join { server, player },
+            GameEvent::PlayerLeft(player) => WsMessage::Leave { server, player },
+            GameEvent::SessionReset { previous_session } => WsMessage::SessionReset {
+                server,
+                previous_session_secs: previous_session.map(|length| length.as_secs()),
+            },
+            GameEvent::ServerStarting => WsMessage::Starting { server },
+            GameEvent::MapLoaded => WsMessage::MapLoaded { server },
+            GameEvent::ChatMessage { author, text } => WsMessage::Chat {
+                server,
+                author,
+                text,
+            },
+            GameEvent::PlayerDied { name, cause } => WsMessage::Death {
+                server,
+                player: name,
+                cause,
+            },
+            GameEvent::PlayerKicked { name, reason } => WsMessage::Kick {
+                server,
+                player: name,
+                reason,
+            },
+            GameEvent::PlayerBanned { name, reason } => WsMessage::Ban {
+                server,
+                player: name,
+                reason,
+            },
+            GameEvent::BannedPlayerAttempt { name } => WsMessage::BanAttempt {
+                server,
+                player: name,
+            },
+            GameEvent::ResearchCompleted { technology } => {
+                WsMessage::Research { server, technology }
+            }
+            GameEvent::Achievement { name, achievement } => WsMessage::Achievement {
+                server,
+                player: name,
+                achievement,
+            },
+            GameEvent::ServerUnresponsive { silent_for } => WsMessage::Unresponsive {
+                server,
+                silent_secs: silent_for.as_secs(),
+            },
+            GameEvent::ServerRecovered => WsMessage::Recovered { server },
+            GameEvent::ServerBecameActive { name } => WsMessage::Active {
+                server,
+                player: name,
+            },
+            GameEvent::ServerBecameEmpty { name } => WsMessage::Empty {
+                server,
+                player: name,
+            },
+            GameEvent::NewPlayer(player) => WsMessage::NewPlayer { server, player },
+            GameEvent::ReturningPlayer { name, away } => WsMessage::ReturningPlayer {
+                server,
+                player: name,
+                away_secs: away.as_secs(),
+            },
+            GameEvent::PlaytimeMilestone { name, hours } => WsMessage::Milestone {
+                server,
+                player: name,
+                hours,
+            },
+            GameEvent::Heartbeat {
+                players,
+                peak,
+                uptime,
+            } => WsMessage::Heartbeat {
+                server,
+                players,
+                peak,
+                uptime_secs: uptime.as_secs(),
+            },
+        };
+        WsEvent { at, message }
+    }
+}
+
+/// Single-page dashboard fed by `/ws`, embedded so the binary is self-contained.
+const INDEX_HTML: &str = include_str!("../static/index.html");
+
+/// Header carrying the shared secret that unlocks `POST /command`.
+const COMMAND_SECRET_HEADER: &str = "x-command-secret";
+
+/// The page's placeholder for the timezone event times are shown in.
+const DISPLAY_TIMEZONE_META: &str = r#"<meta name="display-timezone" content="UTC">"#;
+
+const BASIC_AUTH_CHALLENGE: &str = r#"Basic realm="Factorio Server Dashboard", charset="UTF-8""#;
+
+/// How long open connections get to finish once shutdown starts under TLS.
+const TLS_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+pub struct HttpConfig {
+    pub bind_addr: BindAddr,
+    pub command_secret: Option<String>,
+    pub display_timezone: Tz,
+    pub tls: Option<TlsConfig>,
+    pub basic_auth: Option<BasicAuthConfig>,
+    pub access_log: bool,
+    /// Requests logged per minute, zero for no limit.
+    pub access_log_per_minute: u32,
+    /// Served as is by `GET /config`, already stripped of secrets.
+    pub sanitized_config: serde_json::Value,
+}
+
+fn router(state: Arc<AppState>, config: &HttpConfig) -> Router {
+    let index = Html(INDEX_HTML.replace(
+        DISPLAY_TIMEZONE_META,
+        &DISPLAY_TIMEZONE_META.replace("UTC", config.display_timezone.name()),
+    ));
+    let mut router = Router::new()
+        .route("/", get(move || std::future::ready(index.clone())))
+        .route("/players", get(players))
+        .route("/players/history", get(history))
+        .route("/ws", get(ws))
+        .route("/events.json", get(sse))
+        .route("/health", get(health))
+        .route("/stats", get(stats))
+        .route("/whoami", get(whoami_query).post(whoami_body))
+        .route("/config", {
+            let config = Json(config.sanitized_config.clone());
+            get(move || std::future::ready(config.clone()))
+        })
+        .route("/metrics", get(metrics));
+
+    if state.storage.is_some() {
+        router = router
+            .route("/playtime", get(playtime))
+            .route("/players/{name}/playtime", get(player_playtime))
+            .route("/export/sessions.csv", get(export_sessions));
+    }
+
+    if state.servers.iter().any(|server| server.rcon.is_some()) {
+        match &config.command_secret {
+            Some(secret) => {
+                router = router.route(
+                    "/command",
+                    post(command).route_layer(middleware::from_fn_with_state(
+                        Arc::new(secret.clone()),
+                        require_command_secret,
+                    )),
+                );
+            }
+            None => warn!("COMMAND_SECRET is not set, POST /command is disabled"),
+        }
+    }
+
+    if let Some(credentials) = &config.basic_auth {
+        let credentials = format!("{}:{}", credentials.username, credentials.password);
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(credentials),
+            require_basic_auth,
+        ));
+    }
+
+    // Outermost, so requests turned away by the auth layers are logged too
+    if config.access_log {
+        let access_log = AccessLog {
+            limiter: RateLimiter::per_minute(config.access_log_per_minute),
+            suppressed: 0,
+        };
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(Mutex::new(access_log)),
+            log_request,
+        ));
+    }
+
+    router.with_state(state)
+}
+
+pub async fn serve(state: Arc<AppState>, config: HttpConfig) -> std::io::Result<()> {
+    let router = router(Arc::clone(&state), &config);
+    let mut shutdown = state.shutdown.subscribe();
+
+    let addr = match &config.bind_addr {
+        BindAddr::Tcp(addr) => addr,
+        BindAddr::Unix(path) => return serve_unix(path, router, shutdown).await,
+    };
+    let listener = TcpListener::bind(addr).await?;
+
+    let Some(tls) = &config.tls else {
+        info!(addr = %config.bind_addr, "HTTP server listening");
+        return axum::serve(listener, router)
+            .with_graceful_shutdown(async move {
+                let _ = shutdown.recv().await;
+            })
+            .await;
+    };
+
+    let rustls = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await?;
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            let _ = shutdown.recv().await;
+            handle.graceful_shutdown(Some(TLS_SHUTDOWN_GRACE));
+        }
+    });
+
+    info!(addr = %config.bind_addr, "HTTPS server listening");
+    axum_server::from_tcp_rustls(listener.into_std()?, rustls)?
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+}
+
+#[cfg(unix)]
+async fn serve_unix(
+    path: &str,
+    router: Router,
+    mut shutdown: tokio::sync::broadcast::Receiver<()>,
+) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // A socket left behind by an earlier run would make the bind fail
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+
+    info!(path, "HTTP server listening on Unix socket");
+    let result = axum::serve(listener, router)
+        .with_graceful_shutdown(async move {
+            let _ = shutdown.recv().await;
+        })
+        .await;
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(
+    _path: &str,
+    _router: Router,
+    _shutdown: tokio::sync::broadcast::Receiver<()>,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Unix sockets are not supported on this platform",
+    ))
+}
+
+/// Online players per server, plus the sorted union across all servers.
+async fn online_players(state: &AppState) -> (Vec<String>, Vec<ServerPlayers>) {
+    let now = Utc::now();
+    let mut all_players = Vec::new();
+    let mut servers = Vec::with_capacity(state.servers.len());
+
+    for server in &state.servers {
+        let joined_at = server.online_players.read().await.clone();
+        let mut players: Vec<String> = joined_at.keys().cloned().collect();
+        players.sort_by(|a, b| joined_at[a].cmp(&joined_at[b]).then_with(|| a.cmp(b)));
+        all_players.extend(players.iter().cloned());
+        let online_secs = joined_at
+            .iter()
+            .map(|(name, at)| {
+                (
+                    name.clone(),
+                    (now - *at).to_std().map_or(0, |d| d.as_secs()),
+                )
+            })
+            .collect();
+
+        servers.push(ServerPlayers {
+            server: server.name.clone(),
+            count: players.len(),
+            players,
+            joined_at,
+            online_secs,
+        });
+    }
+
+    all_players.sort();
+    all_players.dedup();
+    (all_players, servers)
+}
+
+async fn players(State(state): State<Arc<AppState>>) -> Json<PlayersResponse> {
+    let (players, servers) = online_players(&state).await;
+
+    Json(PlayersResponse {
+        count: players.len(),
+        players,
+        server_started_at: state.started_at,
+        servers,
+    })
+}
+
+async fn history(State(state): State<Arc<AppState>>) -> Json<Vec<WsEvent>> {
+    Json(
+        state
+            .history
+            .snapshot()
+            .into_iter()
+            .map(WsEvent::from)
+            .collect(),
+    )
+}
+
+async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    let mut servers = Vec::with_capacity(state.servers.len());
+    for server in &state.servers {
+        servers.push(ServerHealth {
+            server: server.name.clone(),
+            log_file_exists: server.reads_stdin() || Path::new(&server.log_path).exists(),
+            last_line_at: *server.last_line_at.read().await,
+            watcher_alive: server.watcher_alive.load(Ordering::Relaxed),
+            peak_players: server.peak_players.load(Ordering::Relaxed),
+        });
+    }
+
+    Json(HealthResponse { servers })
+}
+
+/// `GET /whoami?line=...`, see [`whoami`].
+async fn whoami_query(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WhoamiQuery>,
+) -> Json<WhoamiResponse> {
+    whoami(&state, &query.line)
+}
+
+/// `POST /whoami` with the raw line as the body, which saves URL-encoding it.
+async fn whoami_body(State(state): State<Arc<AppState>>, body: String) -> Json<WhoamiResponse> {
+    whoami(&state, body.trim_end_matches(['\r', '\n']))
+}
+
+fn whoami(state: &AppState, line: &str) -> Json<WhoamiResponse> {
+    let (at, content) = state.patterns.split_timestamp(line);
+    Json(WhoamiResponse {
+        format: state.patterns.preset().to_string(),
+        at,
+        content: content.to_string(),
+        event: state.patterns.classify(content),
+    })
+}
+
+async fn stats(State(state): State<Arc<AppState>>) -> Json<StatsResponse> {
+    let now = Utc::now();
+    let mut online = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut servers = Vec::with_capacity(state.servers.len());
+
+    for server in &state.servers {
+        let players = server.online_players.read().await;
+        let seen_players = server.seen_players.read().await;
+        online.extend(players.keys().cloned());
+        seen.extend(seen_players.iter().cloned());
+
+        servers.push(ServerStats {
+            server: server.name.clone(),
+            players_online: players.len(),
+            peak_players: server.peak_players.load(Ordering::Relaxed),
+            unique_players: seen_players.len(),
+            session_joins: server.session_joins.load(Ordering::Relaxed),
+            session_leaves: server.session_leaves.load(Ordering::Relaxed),
+            uptime_secs: server
+                .session_started_at
+                .read()
+                .await
+                .and_then(|started_at| (now - started_at).to_std().ok())
+                .map(|uptime| uptime.as_secs()),
+        });
+    }
+
+    Json(StatsResponse {
+        players_online: online.len(),
+        unique_players: seen.len(),
+        session_joins: servers.iter().map(|s| s.session_joins).sum(),
+        session_leaves: servers.iter().map(|s| s.session_leaves).sum(),
+        servers,
+    })
+}
+
+async fn playtime(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<PlaytimeEntry>>, StatusCode> {
+    if state.storage.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let totals = state
+        .with_storage(|storage| storage.playtime())
+        .await
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            error!(error = %e, "Failed to compute playtime");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut entries: Vec<PlaytimeEntry> = totals
+        .into_iter()
+        .map(|(player, total)| PlaytimeEntry {
+            player,
+            seconds: total.as_secs(),
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.seconds
+            .cmp(&a.seconds)
+            .then_with(|| a.player.cmp(&b.player))
+    });
+
+    Ok(Json(entries))
+}
+
+async fn player_playtime(
+    State(state): State<Arc<AppState>>,
+    UrlPath(name): UrlPath<String>,
+) -> Result<Json<PlayerPlaytimeResponse>, StatusCode> {
+    if state.storage.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let player = name.clone();
+    let playtime = state
+        .with_storage(move |storage| storage.player_playtime(&player))
+        .await
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            error!(player = %name, error = %e, "Failed to compute playtime");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(PlayerPlaytimeResponse {
+        player: name,
+        seconds: playtime.total.as_secs(),
+        last_seen: playtime.last_seen,
+        online: playtime.online,
+    }))
+}
+
+/// Bytes of CSV collected before they are sent on as one chunk.
+const CSV_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Serialize)]
+struct SessionRow {
+    player: String,
+    joined_at: DateTime<Utc>,
+    left_at: Option<DateTime<Utc>>,
+    /// Up to now for a session that is still open.
+    duration_secs: u64,
+}
+
+/// Passes CSV on to the response body in chunks of [`CSV_CHUNK_SIZE`].
+struct ChunkSender {
+    tx: mpsc::Sender<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+}
+
+impl ChunkSender {
+    fn send_chunk(&mut self) -> std::io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        // A closed channel means the client went away
+        self.tx
+            .blocking_send(Ok(std::mem::take(&mut self.chunk)))
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl std::io::Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() >= CSV_CHUNK_SIZE {
+            self.send_chunk()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_chunk()
+    }
+}
+
+/// Every recorded session as CSV. Rows are streamed out as they are read, so
+/// a long history is never held in memory at once.
+async fn export_sessions(State(state): State<Arc<AppState>>) -> Result<Response, StatusCode> {
+    let storage = state.storage.clone().ok_or(StatusCode::NOT_FOUND)?;
+    let (tx, rx) = mpsc::channel(4);
+
+    tokio::task::spawn_blocking(move || {
+        let mut csv = csv::Writer::from_writer(ChunkSender {
+            tx: tx.clone(),
+            chunk: Vec::new(),
+        });
+        let mut write_error = None;
+        let result = storage.for_each_session(&mut |session| {
+            let ended = session.left_at.unwrap_or_else(Utc::now);
+            let row = SessionRow {
+                duration_secs: (ended - session.joined_at).num_seconds().max(0) as u64,
+                player: session.player,
+                joined_at: session.joined_at,
+                left_at: session.left_at,
+            };
+            write_error = csv.serialize(row).err();
+            write_error.is_none()
+        });
+
+        let result = match (result, write_error) {
+            (Err(e), _) => Err(std::io::Error::other(e)),
+            (Ok(()), Some(e)) => Err(e.into()),
+            (Ok(()), None) => csv.flush(),
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Err(e) => {
+                error!(error = %e, "Failed to export sessions");
+                // Fails the body, so the client can tell the file is incomplete
+                let _ = tx.blocking_send(Err(e));
+            }
+        }
+    });
+
+    let chunks = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"sessions.csv\"",
+            ),
+        ],
+        axum::body::Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+async fn metrics(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, StatusCode> {
+    let body = state.metrics.render().map_err(|e| {
+        error!(error = %e, "Failed to encode metrics");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body))
+}
+
+async fn require_command_secret(
+    State(secret): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let provided = request
+        .headers()
+        .get(COMMAND_SECRET_HEADER)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+
+    if !constant_time_eq(provided, secret.as_bytes()) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Checks the `Authorization: Basic` header against the configured
+/// `username:password`, prompting browsers to log in when it doesn't match.
+async fn require_basic_auth(
+    State(credentials): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+        .and_then(|(_, encoded)| BASE64_STANDARD.decode(encoded.trim()).ok())
+        .unwrap_or_default();
+
+    if !constant_time_eq(&provided, credentials.as_bytes()) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, BASIC_AUTH_CHALLENGE)],
+        )
+            .into_response());
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Rate limit on the access log, and how many requests went unlogged since
+/// the last one that was.
+struct AccessLog {
+    limiter: Option<RateLimiter>,
+    suppressed: u64,
+}
+
+/// Logs each request's method, path, status and latency. The query string is
+/// left out, as it may carry secrets.
+async fn log_request(
+    State(access_log): State<Arc<Mutex<AccessLog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let suppressed = {
+        let access_log = &mut *access_log.lock().unwrap();
+        if let Some(limiter) = &mut access_log.limiter
+            && !limiter.try_acquire()
+        {
+            access_log.suppressed += 1;
+            return response;
+        }
+        std::mem::take(&mut access_log.suppressed)
+    };
+    info!(
+        %method,
+        path,
+        status = response.status().as_u16(),
+        latency_ms,
+        suppressed,
+        "HTTP request"
+    );
+    response
+}
+
+/// Compares secrets without short-circuiting on the first differing byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn command(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CommandRequest>,
+) -> Result<Json<CommandResponse>, (StatusCode, String)> {
+    let server = match &request.server {
+        Some(name) => state.servers.iter().find(|server| &server.name == name),
+        None => state.servers.iter().find(|server| server.rcon.is_some()),
+    }
+    .ok_or((StatusCode::NOT_FOUND, "Unknown server".to_string()))?;
+
+    let rcon = server.rcon.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        format!("RCON is not configured for {}", server.name),
+    ))?;
+
+    info!(server = %server.name, command = %request.command, "Forwarding RCON command");
+    let response = rcon.command(&request.command).await.map_err(|e| {
+        error!(server = %server.name, error = %e, "RCON command failed");
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("RCON command failed: {}", e),
+        )
+    })?;
+
+    Ok(Json(CommandResponse {
+        server: server.name.clone(),
+        response,
+    }))
+}
+
+async fn ws(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    ws.on_upgrade(move |socket| stream_events(socket, state))
+}
+
+async fn stream_events(mut socket: WebSocket, state: Arc<AppState>) {
+    // Subscribe before taking the snapshot so no event falls between the two
+    let mut rx = state.tx.subscribe();
+    let mut shutdown = state.shutdown.subscribe();
+
+    let (players, servers) = online_players(&state).await;
+    let snapshot = WsMessage::Snapshot { players, servers };
+    if send_message(&mut socket, &snapshot).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) => {
+                    if send_message(&mut socket, &WsEvent::from(event)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = shutdown.recv() => break,
+        }
+    }
+}
+
+/// The WebSocket stream for clients that would rather use `EventSource`: the
+/// same messages, one JSON `data` line each.
+async fn sse(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let rx = state.tx.subscribe();
+    let shutdown = state.shutdown.subscribe();
+
+    let (players, servers) = online_players(&state).await;
+    let snapshot = Event::default().json_data(WsMessage::Snapshot { players, servers });
+    let events = stream::unfold((rx, shutdown), |(mut rx, mut shutdown)| async move {
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(event) => {
+                        let event = Event::default().json_data(WsEvent::from(event));
+                        return Some((event, (rx, shutdown)));
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = shutdown.recv() => return None,
+            }
+        }
+    });
+
+    Sse::new(stream::once(std::future::ready(snapshot)).chain(events))
+        .keep_alive(KeepAlive::default())
+}
+
+async fn send_message(socket: &mut WebSocket, message: &impl Serialize) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(message).expect("WebSocket message is serializable");
+    socket.send(Message::Text(json.into())).await
+}
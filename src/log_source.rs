@@ -0,0 +1,275 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use linemux::MuxedLines;
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+
+/// Where the watcher gets new log lines from.
+#[async_trait]
+pub trait LogSource: Send {
+    /// Starts following the log at `path` from its current end, dropping
+    /// whatever was being followed before. Called again after rotation.
+    async fn follow(&mut self, path: &str) -> io::Result<()>;
+
+    /// The next line appended, or `None` once nothing more will come.
+    async fn next_line(&mut self) -> io::Result<Option<String>>;
+}
+
+/// How often to look for a log file that doesn't exist yet.
+const LOG_WAIT_INTERVAL: Duration = Duration::from_secs(2);
+/// How long to wait for it before giving up, leaving the watcher to retry.
+const LOG_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Tails a file on disk through linemux.
+#[derive(Default)]
+pub struct FileLogSource {
+    lines: Option<MuxedLines>,
+}
+
+#[async_trait]
+impl LogSource for FileLogSource {
+    async fn follow(&mut self, path: &str) -> io::Result<()> {
+        self.lines = None;
+        if !Path::new(path).exists() {
+            info!(
+                log_path = path,
+                "Waiting for Factorio to create the log file..."
+            );
+            let appeared = timeout(LOG_WAIT_TIMEOUT, async {
+                while !Path::new(path).exists() {
+                    sleep(LOG_WAIT_INTERVAL).await;
+                }
+            })
+            .await;
+            if appeared.is_err() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "log file did not appear within {}s",
+                        LOG_WAIT_TIMEOUT.as_secs()
+                    ),
+                ));
+            }
+        }
+
+        let mut lines = MuxedLines::new()?;
+        lines.add_file(path).await?;
+        self.lines = Some(lines);
+        Ok(())
+    }
+
+    async fn next_line(&mut self) -> io::Result<Option<String>> {
+        let Some(lines) = &mut self.lines else {
+            return Ok(None);
+        };
+        loop {
+            match lines.next_line().await {
+                Ok(line) => return Ok(line.map(|line| line.line().to_string())),
+                // linemux has already moved past the line, so carry on after it
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    warn!(error = %e, "Skipping log line that isn't valid UTF-8");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Like [`BufRead::lines`], but bytes that aren't valid UTF-8 are replaced
+/// rather than failing the read, so one corrupt line can't stop the rest of
+/// the log from being read.
+pub fn lossy_lines(mut reader: impl BufRead) -> impl Iterator<Item = io::Result<String>> {
+    let mut buf = Vec::new();
+    std::iter::from_fn(move || {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(lossy_line(&buf))),
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+/// Decodes a line read as bytes, dropping its line ending.
+pub fn lossy_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Keeps track of where in the log file the lines handed over by a
+/// [`LogSource`] end. Decoded lines lose their line endings and any bytes
+/// that weren't valid UTF-8, and linemux drops such lines altogether, so
+/// their lengths can't be summed. Instead the same bytes are read alongside.
+pub struct LogPosition {
+    reader: Option<BufReader<File>>,
+    offset: u64,
+    buf: Vec<u8>,
+}
+
+impl LogPosition {
+    /// Starts at `offset` into the file at `path`. A file that can't be
+    /// opened leaves the position stuck at `offset`.
+    pub fn open(path: &str, offset: u64) -> Self {
+        let reader = File::open(path)
+            .and_then(|mut file| file.seek(SeekFrom::Start(offset)).map(|_| file))
+            .map(BufReader::new)
+            .ok();
+        Self {
+            reader,
+            offset,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Moves past `line`, and any lines before it that the source skipped,
+    /// returning the offset just after it. If `line` can't be found the
+    /// position stays where it was.
+    pub fn advance(&mut self, line: &str) -> u64 {
+        let Some(reader) = &mut self.reader else {
+            return self.offset;
+        };
+        let mut offset = self.offset;
+        loop {
+            self.buf.clear();
+            match reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => offset += read as u64,
+            }
+            if lossy_line(&self.buf) == line {
+                self.offset = offset;
+                return offset;
+            }
+        }
+        if reader.seek(SeekFrom::Start(self.offset)).is_err() {
+            self.reader = None;
+        }
+        self.offset
+    }
+}
+
+/// Spots a line identical to the one right before it, which some setups
+/// write twice, e.g. around a rotation.
+#[derive(Default)]
+pub struct RepeatFilter {
+    last: Option<String>,
+}
+
+impl RepeatFilter {
+    /// Whether `line` repeats the previous one. Remembers it either way.
+    pub fn is_repeat(&mut self, line: &str) -> bool {
+        if self.last.as_deref() == Some(line) {
+            return true;
+        }
+        self.last = Some(line.to_string());
+        false
+    }
+}
+
+/// Lines pushed through a channel, for tests that shouldn't touch the disk.
+#[cfg(test)]
+pub struct MemoryLogSource {
+    lines: tokio::sync::mpsc::UnboundedReceiver<String>,
+}
+
+#[cfg(test)]
+impl MemoryLogSource {
+    /// The source, and the sender to push lines into it with. Dropping the
+    /// sender ends the log.
+    pub fn new() -> (Self, tokio::sync::mpsc::UnboundedSender<String>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        (Self { lines: rx }, tx)
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl LogSource for MemoryLogSource {
+    async fn follow(&mut self, _path: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn next_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.lines.recv().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_consecutive_duplicates() {
+        let mut repeats = RepeatFilter::default();
+        assert!(!repeats.is_repeat("JOIN|1|Alice"));
+        assert!(repeats.is_repeat("JOIN|1|Alice"));
+        assert!(repeats.is_repeat("JOIN|1|Alice"));
+    }
+
+    #[test]
+    fn allows_a_line_again_once_another_came_between() {
+        let mut repeats = RepeatFilter::default();
+        assert!(!repeats.is_repeat("[CHAT] Alice: gg"));
+        assert!(!repeats.is_repeat("[CHAT] Bob: gg"));
+        assert!(!repeats.is_repeat("[CHAT] Alice: gg"));
+    }
+
+    #[tokio::test]
+    async fn follow_waits_for_the_file_to_appear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        let created = path.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(100)).await;
+            std::fs::write(created, "").unwrap();
+        });
+
+        let mut source = FileLogSource::default();
+        source.follow(path.to_str().unwrap()).await.unwrap();
+        assert!(path.exists());
+        assert!(source.lines.is_some());
+    }
+
+    #[test]
+    fn position_counts_the_bytes_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        let mut log = b"JOIN|1|Alice\r\n".to_vec();
+        log.extend_from_slice(b"[CHAT] Bob: \xff\xfe\n");
+        log.extend_from_slice(b"[CHAT] Carol: caf\xc3\xa9\n");
+        log.extend_from_slice(b"LEAVE|2|Alice\n");
+        std::fs::write(&path, &log).unwrap();
+
+        let mut position = LogPosition::open(path.to_str().unwrap(), 0);
+        assert_eq!(position.advance("JOIN|1|Alice"), 14);
+        // linemux skips the line that isn't valid UTF-8
+        position.advance("[CHAT] Carol: café");
+        assert_eq!(position.advance("LEAVE|2|Alice"), log.len() as u64);
+    }
+
+    #[test]
+    fn position_stays_put_for_a_line_not_in_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("console.log");
+        std::fs::write(&path, "JOIN|1|Alice\nLEAVE|2|Alice\n").unwrap();
+
+        let mut position = LogPosition::open(path.to_str().unwrap(), 0);
+        assert_eq!(position.advance("JOIN|1|Bob"), 0);
+        assert_eq!(position.advance("JOIN|1|Alice"), 13);
+    }
+
+    #[test]
+    fn compares_whole_lines() {
+        let mut repeats = RepeatFilter::default();
+        assert!(!repeats.is_repeat("JOIN|1|Alice"));
+        assert!(!repeats.is_repeat("JOIN|1|Alice "));
+        assert!(!repeats.is_repeat(""));
+        assert!(repeats.is_repeat(""));
+    }
+}
@@ -1,238 +1,2299 @@
+mod cli;
+mod config;
+mod debounce;
+mod demo;
+mod history;
+mod http;
+mod log_source;
+mod metrics;
+mod notifier;
+mod parser;
+mod patterns;
+mod player_aliases;
+mod player_filter;
+mod rate_limit;
+mod rcon;
+mod replay;
+mod storage;
+mod templates;
+#[cfg(test)]
+mod tests;
+mod watch_error;
+
 use std::{
-    collections::HashSet,
-    env,
+    collections::{HashMap, HashSet},
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
     path::Path,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
     time::Duration,
 };
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use clap::Parser;
+use cli::Cli;
+use config::{Config, NotifyToggles, Settings, StorageBackend, optional_env};
+use debounce::{Debouncer, JoinGrace};
 use dotenv::dotenv;
-use linemux::MuxedLines;
-use reqwest::Client;
-use serde::Serialize;
+use futures_util::{StreamExt, stream};
+use history::EventHistory;
+use log_source::{FileLogSource, LogPosition, LogSource, RepeatFilter, lossy_line, lossy_lines};
+use metrics::Metrics;
+use notifier::{
+    DiscordNotifier, DryRunNotifier, MatrixNotifier, Notification, Notifier, SlackNotifier,
+    TelegramNotifier, WebhookNotifier, escape_html, http_client, split_message,
+};
+use parser::LogEvent;
+use patterns::{LogPatterns, ParsedLine};
+use player_aliases::PlayerAliases;
+use player_filter::PlayerFilter;
+use rate_limit::RateLimiter;
+use rcon::RconClient;
+use storage::{MemoryStorage, PlayerAction, SqliteStorage, Storage};
+use templates::MessageTemplates;
 use tokio::{
     sync::{
         RwLock,
-        broadcast::{Receiver, Sender},
+        broadcast::{
+            Receiver, Sender,
+            error::{RecvError, TryRecvError},
+        },
+        mpsc,
     },
     time::sleep,
 };
+use tracing::{debug, error, info, instrument, warn};
+use tracing_subscriber::EnvFilter;
+use watch_error::WatchError;
+
+/// `FACTORIO_LOG_PATH` entry meaning the log is piped in on stdin.
+const STDIN_LOG_PATH: &str = "-";
+
+/// Live state of a single watched Factorio server.
+struct ServerState {
+    name: String,
+    log_path: String,
+    /// Who is online, and since when.
+    online_players: RwLock<HashMap<String, DateTime<Utc>>>,
+    /// Most players online at once since the last session reset.
+    peak_players: AtomicUsize,
+    /// When the current session started, if the dashboard saw it happen.
+    session_started_at: RwLock<Option<DateTime<Utc>>>,
+    /// Joins and leaves seen live since the last session reset.
+    session_joins: AtomicUsize,
+    session_leaves: AtomicUsize,
+    /// Everyone who has been online since the dashboard started.
+    seen_players: RwLock<HashSet<String>>,
+    last_line_at: RwLock<Option<DateTime<Utc>>>,
+    /// Set while the watchdog considers the server hung.
+    unresponsive: AtomicBool,
+    watcher_alive: AtomicBool,
+    rcon: Option<RconClient>,
+    /// Drop a line that exactly repeats the one before it.
+    skip_repeated_lines: bool,
+    /// Announce a leave even when the player wasn't known to be online.
+    notify_unknown_leaves: bool,
+}
+
+impl ServerState {
+    fn new(name: String, log_path: String, rcon: Option<RconClient>) -> Self {
+        Self {
+            name,
+            log_path,
+            online_players: RwLock::new(HashMap::new()),
+            peak_players: AtomicUsize::new(0),
+            session_started_at: RwLock::new(None),
+            session_joins: AtomicUsize::new(0),
+            session_leaves: AtomicUsize::new(0),
+            seen_players: RwLock::new(HashSet::new()),
+            last_line_at: RwLock::new(None),
+            unresponsive: AtomicBool::new(false),
+            watcher_alive: AtomicBool::new(false),
+            rcon,
+            skip_repeated_lines: false,
+            notify_unknown_leaves: false,
+        }
+    }
+
+    fn reads_stdin(&self) -> bool {
+        self.log_path == STDIN_LOG_PATH
+    }
+
+    fn reset_session_counters(&self) {
+        self.peak_players.store(0, Ordering::Relaxed);
+        self.session_joins.store(0, Ordering::Relaxed);
+        self.session_leaves.store(0, Ordering::Relaxed);
+    }
+}
 
 struct AppState {
-    online_players: RwLock<HashSet<String>>,
-    tx: Sender<GameEvent>,
+    servers: Vec<Arc<ServerState>>,
+    tx: Sender<ServerEvent>,
+    shutdown: Sender<()>,
+    started_at: DateTime<Utc>,
+    storage: Option<Arc<dyn Storage>>,
+    metrics: Metrics,
+    debouncer: Debouncer,
+    /// Holds joins back until the player has stayed online for `JOIN_GRACE_SECS`.
+    join_grace: JoinGrace,
+    patterns: LogPatterns,
+    history: EventHistory,
 }
 
 impl AppState {
-    fn new(tx: Sender<GameEvent>) -> Self {
+    fn new(
+        servers: Vec<Arc<ServerState>>,
+        tx: Sender<ServerEvent>,
+        shutdown: Sender<()>,
+        storage: Option<Arc<dyn Storage>>,
+        debounce_window: Duration,
+        join_grace: Duration,
+        patterns: LogPatterns,
+        history_size: usize,
+    ) -> Self {
         Self {
-            online_players: RwLock::new(HashSet::new()),
-            tx: tx,
+            servers,
+            tx,
+            shutdown,
+            started_at: Utc::now(),
+            storage,
+            metrics: Metrics::new(),
+            debouncer: Debouncer::new(debounce_window),
+            join_grace: JoinGrace::new(join_grace),
+            patterns,
+            history: EventHistory::new(history_size),
+        }
+    }
+
+    fn send(&self, server: &ServerState, event: GameEvent, at: DateTime<Utc>) {
+        let _ = self.tx.send(ServerEvent {
+            server: server.name.clone(),
+            at,
+            online: self.online_count(server),
+            event,
+        });
+    }
+
+    /// Read from the gauge, which is kept current even while the roster is
+    /// locked for writing.
+    fn online_count(&self, server: &ServerState) -> usize {
+        self.metrics.online_players(&server.name).get() as usize
+    }
+
+    /// Like `send`, but lets the debouncer swallow join/leave flapping.
+    fn send_debounced(
+        &self,
+        server: &ServerState,
+        player: &str,
+        event: GameEvent,
+        at: DateTime<Utc>,
+    ) {
+        let event = ServerEvent {
+            server: server.name.clone(),
+            at,
+            online: self.online_count(server),
+            event,
+        };
+        self.debouncer.dispatch(&self.tx, player, event);
+    }
+
+    /// Announces a join followed by `followers`, the events it set off, after
+    /// the join grace period if one is set. Otherwise the join goes through
+    /// `send_debounced`.
+    fn send_join(
+        &self,
+        server: &ServerState,
+        player: &str,
+        at: DateTime<Utc>,
+        followers: Vec<GameEvent>,
+    ) {
+        let join = GameEvent::PlayerJoined(player.to_string());
+        if !self.join_grace.is_enabled() {
+            self.send_debounced(server, player, join, at);
+            for event in followers {
+                self.send(server, event, at);
+            }
+            return;
+        }
+        let online = self.online_count(server);
+        let events = std::iter::once(join)
+            .chain(followers)
+            .map(|event| ServerEvent {
+                server: server.name.clone(),
+                at,
+                online,
+                event,
+            })
+            .collect();
+        self.join_grace
+            .delay(&self.tx, &server.name, player, events);
+    }
+
+    /// Announces a leave, unless the join it ends was never announced.
+    /// Returns whether it was.
+    fn send_leave(&self, server: &ServerState, player: &str, at: DateTime<Utc>) -> bool {
+        if self.join_grace.cancel(&server.name, player) {
+            return false;
+        }
+        self.send_debounced(
+            server,
+            player,
+            GameEvent::PlayerLeft(player.to_string()),
+            at,
+        );
+        true
+    }
+
+    /// Runs `f` against the storage on the blocking pool, since any call may
+    /// end up writing to disk. `None` without storage.
+    async fn with_storage<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&dyn Storage) -> T + Send + 'static,
+    ) -> Option<T> {
+        let storage = Arc::clone(self.storage.as_ref()?);
+        match tokio::task::spawn_blocking(move || f(storage.as_ref())).await {
+            Ok(result) => Some(result),
+            Err(e) => {
+                error!(error = %e, "Storage call panicked");
+                None
+            }
+        }
+    }
+
+    /// Records the same action for every player in `names`.
+    async fn record_events(&self, names: Vec<String>, action: PlayerAction, at: DateTime<Utc>) {
+        let failed = self
+            .with_storage(move |storage| {
+                names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let e = storage.record_event(&name, action, at).err()?;
+                        Some((name, e))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await;
+        for (name, e) in failed.into_iter().flatten() {
+            error!(player = %name, error = %e, "Failed to persist event");
+        }
+    }
+
+    /// The byte offset to resume `server`'s log from, provided it was saved
+    /// for the file that is there now and the file hasn't shrunk since.
+    fn saved_offset(&self, server: &ServerState, identity: Option<LogFileIdentity>) -> Option<u64> {
+        let storage = self.storage.as_ref()?;
+        let identity = identity?;
+        match storage.log_offset(&server.log_path) {
+            Ok(Some((inode, offset))) if inode == identity.inode && offset <= identity.len => {
+                Some(offset)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error!(error = %e, "Failed to load log offset");
+                None
+            }
+        }
+    }
+
+    fn save_offset(&self, server: &ServerState, identity: Option<LogFileIdentity>, offset: u64) {
+        if let (Some(storage), Some(identity)) = (&self.storage, identity) {
+            storage.save_log_offset(&server.log_path, identity.inode, offset);
+        }
+    }
+
+    async fn clear_active_players(&self, server: &ServerState, at: DateTime<Utc>) {
+        let mut players = server.online_players.write().await;
+        // Everyone still online was disconnected by the restart
+        let names: Vec<String> = players.drain().map(|(name, _)| name).collect();
+        for name in &names {
+            self.join_grace.cancel(&server.name, name);
         }
+        server.reset_session_counters();
+        self.metrics.online_players(&server.name).set(0);
+        self.metrics.session_resets(&server.name).inc();
+        drop(players);
+        self.record_events(names, PlayerAction::Leave, at).await;
+
+        let previous_session = server
+            .session_started_at
+            .write()
+            .await
+            .replace(at)
+            .and_then(|started_at| (at - started_at).to_std().ok());
+        self.send(server, GameEvent::SessionReset { previous_session }, at);
     }
 
-    async fn clear_active_players(&self) {
-        let mut players = self.online_players.write().await;
-        players.clear();
-        let _ = self.tx.send(GameEvent::SessionReset);
+    async fn add_player(&self, server: &ServerState, name: &str, at: DateTime<Utc>) {
+        let mut players = server.online_players.write().await;
+        if !players.contains_key(name) {
+            players.insert(name.to_string(), at);
+            info!(player = %name, server = %server.name, "Detected join event");
+            self.metrics.joins(&server.name).inc();
+            server.session_joins.fetch_add(1, Ordering::Relaxed);
+            server.seen_players.write().await.insert(name.to_string());
+            server
+                .peak_players
+                .fetch_max(players.len(), Ordering::Relaxed);
+            self.metrics
+                .online_players(&server.name)
+                .set(players.len() as i64);
+            let mut followers = Vec::new();
+            if players.len() == 1 {
+                let name = name.to_string();
+                followers.push(GameEvent::ServerBecameActive { name });
+            }
+            // The roster isn't held up while the database is
+            drop(players);
+            self.record_events(vec![name.to_string()], PlayerAction::Join, at)
+                .await;
+            followers.extend(self.milestones(name, at).await);
+            self.send_join(server, name, at, followers);
+        }
     }
 
-    async fn add_player(&self, name: &str) {
-        let mut players = self.online_players.write().await;
-        if players.insert(name.to_string()) {
-            println!("Detected join event for: {}", name);
-            let _ = self.tx.send(GameEvent::PlayerJoined(name.to_string()));
+    /// The welcome and playtime events a join is worth.
+    async fn milestones(&self, name: &str, at: DateTime<Utc>) -> Vec<GameEvent> {
+        let player = name.to_string();
+        let Some(milestones) = self
+            .with_storage(move |storage| storage.join_milestones(&player, at))
+            .await
+        else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        match milestones {
+            Ok(milestones) => {
+                if milestones.first_join {
+                    events.push(GameEvent::NewPlayer(name.to_string()));
+                }
+                if let Some(away) = milestones.away {
+                    let name = name.to_string();
+                    events.push(GameEvent::ReturningPlayer { name, away });
+                }
+                if let Some(hours) = milestones.hours {
+                    let name = name.to_string();
+                    events.push(GameEvent::PlaytimeMilestone { name, hours });
+                }
+            }
+            Err(e) => error!(player = %name, error = %e, "Failed to check milestones"),
         }
+        events
     }
 
-    async fn remove_player(&self, name: &str) {
-        let mut players = self.online_players.write().await;
-        if players.remove(name) {
-            println!("Detected leave event for: {}", name);
-            let _ = self.tx.send(GameEvent::PlayerLeft(name.to_string()));
+    async fn remove_player(&self, server: &ServerState, name: &str, at: DateTime<Utc>) {
+        let mut players = server.online_players.write().await;
+        if players.remove(name).is_some() {
+            info!(player = %name, server = %server.name, "Detected leave event");
+            self.metrics.leaves(&server.name).inc();
+            server.session_leaves.fetch_add(1, Ordering::Relaxed);
+            self.metrics
+                .online_players(&server.name)
+                .set(players.len() as i64);
+            let empty = players.is_empty();
+            drop(players);
+            self.record_events(vec![name.to_string()], PlayerAction::Leave, at)
+                .await;
+            // Nobody heard of the join, so the server never seemed active either
+            if self.send_leave(server, name, at) && empty {
+                let name = name.to_string();
+                self.send(server, GameEvent::ServerBecameEmpty { name }, at);
+            }
+        } else {
+            // Usually someone who joined before the dashboard started reading the log
+            debug!(player = %name, server = %server.name, "Leave for a player who wasn't online");
+            if server.notify_unknown_leaves {
+                self.send_debounced(server, name, GameEvent::PlayerLeft(name.to_string()), at);
+            }
         }
     }
+
+    fn report_death(
+        &self,
+        server: &ServerState,
+        name: String,
+        cause: Option<String>,
+        at: DateTime<Utc>,
+    ) {
+        info!(player = %name, server = %server.name, "Detected death event");
+        self.send(server, GameEvent::PlayerDied { name, cause }, at);
+    }
+
+    fn report_kick(
+        &self,
+        server: &ServerState,
+        name: String,
+        reason: Option<String>,
+        at: DateTime<Utc>,
+    ) {
+        info!(player = %name, server = %server.name, "Detected kick event");
+        self.send(server, GameEvent::PlayerKicked { name, reason }, at);
+    }
+
+    fn report_ban(
+        &self,
+        server: &ServerState,
+        name: String,
+        reason: Option<String>,
+        at: DateTime<Utc>,
+    ) {
+        info!(player = %name, server = %server.name, "Detected ban event");
+        self.send(server, GameEvent::PlayerBanned { name, reason }, at);
+    }
+
+    fn report_ban_attempt(&self, server: &ServerState, name: String, at: DateTime<Utc>) {
+        warn!(player = %name, server = %server.name, "Banned player tried to join");
+        self.send(server, GameEvent::BannedPlayerAttempt { name }, at);
+    }
+
+    fn report_research(&self, server: &ServerState, technology: String, at: DateTime<Utc>) {
+        info!(%technology, server = %server.name, "Detected research completion");
+        self.send(server, GameEvent::ResearchCompleted { technology }, at);
+    }
+
+    fn report_achievement(
+        &self,
+        server: &ServerState,
+        name: String,
+        achievement: String,
+        at: DateTime<Utc>,
+    ) {
+        info!(player = %name, %achievement, server = %server.name, "Detected achievement");
+        self.send(server, GameEvent::Achievement { name, achievement }, at);
+    }
+
+    fn relay_chat(&self, server: &ServerState, author: &str, text: &str, at: DateTime<Utc>) {
+        self.send(
+            server,
+            GameEvent::ChatMessage {
+                author: author.to_string(),
+                text: text.to_string(),
+            },
+            at,
+        );
+    }
 }
 
 #[derive(Clone)]
 enum GameEvent {
     PlayerJoined(String),
     PlayerLeft(String),
-    SessionReset,
+    SessionReset {
+        previous_session: Option<Duration>,
+    },
+    /// The server process started and is loading the map.
+    ServerStarting,
+    /// The map finished loading; players can join once the session starts.
+    MapLoaded,
+    ChatMessage {
+        author: String,
+        text: String,
+    },
+    PlayerDied {
+        name: String,
+        cause: Option<String>,
+    },
+    PlayerKicked {
+        name: String,
+        reason: Option<String>,
+    },
+    PlayerBanned {
+        name: String,
+        reason: Option<String>,
+    },
+    /// `name` is banned and was refused on trying to join.
+    BannedPlayerAttempt {
+        name: String,
+    },
+    ResearchCompleted {
+        technology: String,
+    },
+    /// `name` unlocked an achievement.
+    Achievement {
+        name: String,
+        achievement: String,
+    },
+    /// The log went quiet while players were online.
+    ServerUnresponsive {
+        silent_for: Duration,
+    },
+    /// The log is being written to again after `ServerUnresponsive`.
+    ServerRecovered,
+    /// `name` is the first player online since the server was last empty.
+    ServerBecameActive {
+        name: String,
+    },
+    /// `name` was the last player online.
+    ServerBecameEmpty {
+        name: String,
+    },
+    /// First join ever by this player, as far as the database knows.
+    NewPlayer(String),
+    /// `name` is back after not being seen for `away`, at least
+    /// `RETURNING_PLAYER_DAYS`.
+    ReturningPlayer {
+        name: String,
+        away: Duration,
+    },
+    /// `name`'s total playtime has passed `hours`.
+    PlaytimeMilestone {
+        name: String,
+        hours: u64,
+    },
+    Heartbeat {
+        players: usize,
+        peak: usize,
+        uptime: Duration,
+    },
 }
 
-#[derive(Serialize)]
-struct TelegramPayload {
-    chat_id: String,
-    text: String,
-    parse_mode: String,
+impl GameEvent {
+    /// Every value [`GameEvent::kind`] can return.
+    const KINDS: &[&str] = &[
+        "join",
+        "leave",
+        "session_reset",
+        "starting",
+        "map_loaded",
+        "chat",
+        "death",
+        "kick",
+        "ban",
+        "ban_attempt",
+        "research",
+        "achievement",
+        "unresponsive",
+        "recovered",
+        "active",
+        "empty",
+        "new_player",
+        "returning_player",
+        "milestone",
+        "heartbeat",
+    ];
+
+    /// Short name of the event kind, as exposed to webhooks.
+    fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::PlayerJoined(_) => "join",
+            GameEvent::PlayerLeft(_) => "leave",
+            GameEvent::SessionReset { .. } => "session_reset",
+            GameEvent::ServerStarting => "starting",
+            GameEvent::MapLoaded => "map_loaded",
+            GameEvent::ChatMessage { .. } => "chat",
+            GameEvent::PlayerDied { .. } => "death",
+            GameEvent::PlayerKicked { .. } => "kick",
+            GameEvent::PlayerBanned { .. } => "ban",
+            GameEvent::BannedPlayerAttempt { .. } => "ban_attempt",
+            GameEvent::ResearchCompleted { .. } => "research",
+            GameEvent::Achievement { .. } => "achievement",
+            GameEvent::ServerUnresponsive { .. } => "unresponsive",
+            GameEvent::ServerRecovered => "recovered",
+            GameEvent::ServerBecameActive { .. } => "active",
+            GameEvent::ServerBecameEmpty { .. } => "empty",
+            GameEvent::NewPlayer(_) => "new_player",
+            GameEvent::ReturningPlayer { .. } => "returning_player",
+            GameEvent::PlaytimeMilestone { .. } => "milestone",
+            GameEvent::Heartbeat { .. } => "heartbeat",
+        }
+    }
+
+    /// The player the event is about, if it concerns a single one.
+    fn player(&self) -> Option<&str> {
+        match self {
+            GameEvent::PlayerJoined(name)
+            | GameEvent::PlayerLeft(name)
+            | GameEvent::PlayerDied { name, .. }
+            | GameEvent::PlayerKicked { name, .. }
+            | GameEvent::PlayerBanned { name, .. }
+            | GameEvent::BannedPlayerAttempt { name }
+            | GameEvent::Achievement { name, .. }
+            | GameEvent::ServerBecameActive { name }
+            | GameEvent::ServerBecameEmpty { name }
+            | GameEvent::NewPlayer(name)
+            | GameEvent::ReturningPlayer { name, .. }
+            | GameEvent::PlaytimeMilestone { name, .. } => Some(name),
+            GameEvent::ChatMessage { author, .. } => Some(author),
+            GameEvent::SessionReset { .. }
+            | GameEvent::ServerStarting
+            | GameEvent::MapLoaded
+            | GameEvent::ResearchCompleted { .. }
+            | GameEvent::ServerUnresponsive { .. }
+            | GameEvent::ServerRecovered
+            | GameEvent::Heartbeat { .. } => None,
+        }
+    }
+
+    /// Mutable access to the player the event is about.
+    fn player_mut(&mut self) -> Option<&mut String> {
+        match self {
+            GameEvent::PlayerJoined(name)
+            | GameEvent::PlayerLeft(name)
+            | GameEvent::PlayerDied { name, .. }
+            | GameEvent::PlayerKicked { name, .. }
+            | GameEvent::PlayerBanned { name, .. }
+            | GameEvent::BannedPlayerAttempt { name }
+            | GameEvent::Achievement { name, .. }
+            | GameEvent::ServerBecameActive { name }
+            | GameEvent::ServerBecameEmpty { name }
+            | GameEvent::NewPlayer(name)
+            | GameEvent::ReturningPlayer { name, .. }
+            | GameEvent::PlaytimeMilestone { name, .. } => Some(name),
+            GameEvent::ChatMessage { author, .. } => Some(author),
+            GameEvent::SessionReset { .. }
+            | GameEvent::ServerStarting
+            | GameEvent::MapLoaded
+            | GameEvent::ResearchCompleted { .. }
+            | GameEvent::ServerUnresponsive { .. }
+            | GameEvent::ServerRecovered
+            | GameEvent::Heartbeat { .. } => None,
+        }
+    }
+
+    /// Escapes every value taken from the log, so it can go into the HTML
+    /// that notifications are written in.
+    fn escape_html(&mut self) {
+        let values = match self {
+            GameEvent::PlayerJoined(name)
+            | GameEvent::PlayerLeft(name)
+            | GameEvent::BannedPlayerAttempt { name }
+            | GameEvent::ServerBecameActive { name }
+            | GameEvent::ServerBecameEmpty { name }
+            | GameEvent::NewPlayer(name)
+            | GameEvent::ReturningPlayer { name, .. }
+            | GameEvent::PlaytimeMilestone { name, .. } => vec![name],
+            GameEvent::ChatMessage { author, text } => vec![author, text],
+            GameEvent::PlayerDied {
+                name,
+                cause: reason,
+            }
+            | GameEvent::PlayerKicked { name, reason }
+            | GameEvent::PlayerBanned { name, reason } => {
+                let mut values = vec![name];
+                values.extend(reason.as_mut());
+                values
+            }
+            GameEvent::ResearchCompleted { technology } => vec![technology],
+            GameEvent::Achievement { name, achievement } => vec![name, achievement],
+            GameEvent::SessionReset { .. }
+            | GameEvent::ServerStarting
+            | GameEvent::MapLoaded
+            | GameEvent::ServerUnresponsive { .. }
+            | GameEvent::ServerRecovered
+            | GameEvent::Heartbeat { .. } => Vec::new(),
+        };
+        for value in values {
+            *value = escape_html(value);
+        }
+    }
+}
+
+/// A `GameEvent` tagged with the server it happened on and when.
+#[derive(Clone)]
+struct ServerEvent {
+    server: String,
+    /// Taken from the log line when it carries a timestamp, otherwise when it was read.
+    at: DateTime<Utc>,
+    /// Players online on the server once the event took effect.
+    online: usize,
+    event: GameEvent,
+}
+
+/// How the notification worker turns events into messages.
+struct WorkerOptions {
+    /// Swapped out on SIGHUP, see [`reload_on_sighup`].
+    rules: Arc<std::sync::RwLock<Arc<NotifyRules>>>,
+    prefix_server: bool,
+    limiter: Option<RateLimiter>,
+    join_batch_window: Duration,
+    reconnect_grace: Duration,
+    /// When nonzero, events are summed up once per interval instead of sent one by one.
+    digest_interval: Duration,
+    /// Timezone for times shown in notifications.
+    display_timezone: Tz,
+    /// Where notifications that failed to send wait to be retried.
+    queue: Option<Arc<dyn Storage>>,
+    /// Notifiers sent to at the same time, zero for all of them.
+    delivery_concurrency: usize,
+}
+
+impl WorkerOptions {
+    /// The rules in effect right now. Taken afresh for every batch, so a
+    /// reload applies from the next notification on.
+    fn rules(&self) -> Arc<NotifyRules> {
+        Arc::clone(&self.rules.read().unwrap())
+    }
 }
 
-struct TelegramNotifier {
-    token: String,
-    chat_id: String,
-    client: Client,
+/// The notification settings that can be changed without a restart.
+struct NotifyRules {
+    templates: MessageTemplates,
+    filter: EventFilter,
+    aliases: PlayerAliases,
 }
 
-impl TelegramNotifier {
-    fn new(token: String, chat_id: String) -> Self {
+impl NotifyRules {
+    fn new(config: &Config) -> Self {
         Self {
-            token,
-            chat_id,
-            client: Client::new(),
+            templates: config.templates.clone(),
+            filter: EventFilter {
+                players: PlayerFilter::new(
+                    &config.notify_ignore_players,
+                    &config.notify_only_players,
+                ),
+                notify_on: config.notify_on.clone(),
+                chat_keywords: config
+                    .chat_notify_keywords
+                    .iter()
+                    .map(|keyword| keyword.to_lowercase())
+                    .collect(),
+            },
+            aliases: PlayerAliases::new(&config.player_aliases),
+        }
+    }
+}
+
+/// Decides which events are worth a notification at all.
+struct EventFilter {
+    players: PlayerFilter,
+    notify_on: NotifyToggles,
+    /// Lowercased words one of which chat must contain to be notified about,
+    /// empty for all chat.
+    chat_keywords: Vec<String>,
+}
+
+impl EventFilter {
+    fn allows(&self, event: &GameEvent) -> bool {
+        self.notify_on.allows(event) && self.players.allows_event(event) && self.allows_chat(event)
+    }
+
+    fn allows_chat(&self, event: &GameEvent) -> bool {
+        let GameEvent::ChatMessage { text, .. } = event else {
+            return true;
+        };
+        if self.chat_keywords.is_empty() {
+            return true;
         }
+        let text = text.to_lowercase();
+        self.chat_keywords
+            .iter()
+            .any(|keyword| text.contains(keyword.as_str()))
     }
+}
 
-    async fn notify(&self, message: &str) {
-        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+#[instrument(skip_all)]
+async fn notification_worker(
+    mut rx: Receiver<ServerEvent>,
+    notifiers: Vec<Box<dyn Notifier + Send + Sync>>,
+    mut options: WorkerOptions,
+    mut shutdown: Receiver<()>,
+) {
+    info!("Notification worker is started");
+    if !options.digest_interval.is_zero() {
+        return digest_worker(rx, notifiers, options, shutdown).await;
+    }
 
-        let payload = TelegramPayload {
-            chat_id: self.chat_id.clone(),
-            text: message.to_string(),
-            parse_mode: "HTML".to_string(),
+    let queue = options.queue.as_deref();
+    // The first tick fires immediately, delivering whatever an earlier run left queued
+    let mut queue_retry = tokio::time::interval(QUEUE_RETRY_INTERVAL);
+    let mut pending = Vec::new();
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => event,
+            _ = queue_retry.tick(), if queue.is_some() => {
+                retry_queued(&notifiers, queue).await;
+                continue;
+            }
+            _ = shutdown.recv() => break,
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(RecvError::Lagged(dropped)) => {
+                warn!(
+                    dropped,
+                    "Notification worker fell behind, events were dropped"
+                );
+                continue;
+            }
+            Err(RecvError::Closed) => break,
         };
+        // A reset opens the grace window even when it isn't notified about itself
+        let reconnecting = (!options.reconnect_grace.is_zero()
+            && matches!(event.event, GameEvent::SessionReset { .. }))
+        .then(|| event.server.clone());
+        let batch_window = if reconnecting.is_some() {
+            options.reconnect_grace
+        } else if matches!(event.event, GameEvent::PlayerJoined(_)) {
+            options.join_batch_window
+        } else {
+            Duration::ZERO
+        };
+        let mut events = Vec::new();
+        if options.rules().filter.allows(&event.event) {
+            events.push(event);
+        } else if reconnecting.is_none() {
+            continue;
+        }
 
-        let response = self.client.post(url).json(&payload).send().await;
-        match response {
-            Ok(res) => {
-                if !res.status().is_success() {
-                    let err_body = res.text().await.unwrap_or_default();
-                    eprintln!("Telegram API Error: {}", err_body);
+        let mut stopping = false;
+        if !batch_window.is_zero() {
+            let deadline = tokio::time::Instant::now() + batch_window;
+            loop {
+                let event = tokio::select! {
+                    event = tokio::time::timeout_at(deadline, rx.recv()) => match event {
+                        Ok(event) => event,
+                        Err(_) => break,
+                    },
+                    _ = shutdown.recv() => {
+                        stopping = true;
+                        break;
+                    }
+                };
+                match event {
+                    Ok(event) if options.rules().filter.allows(&event.event) => events.push(event),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(dropped)) => {
+                        warn!(
+                            dropped,
+                            "Notification worker fell behind, events were dropped"
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
                 }
             }
-            Err(e) => eprintln!("HTTP Request Error: {}", e),
+        }
+        if stopping {
+            pending = events;
+            break;
+        }
+        if let Some(limiter) = options.limiter.as_mut()
+            && !limiter.try_acquire()
+        {
+            // Out of budget: fold everything queued so far into the next message
+            let stopping = tokio::select! {
+                _ = limiter.acquire() => false,
+                _ = shutdown.recv() => true,
+            };
+            drain_queued(&mut rx, &options.rules().filter, &mut events);
+            if stopping {
+                pending = events;
+                break;
+            }
+        }
+
+        let notification = match &reconnecting {
+            Some(server) => render_reconnects(&options, server, events),
+            None => render(&options, events),
+        };
+        if !notification.text.is_empty() {
+            deliver(
+                &notifiers,
+                &notification,
+                queue,
+                options.delivery_concurrency,
+            )
+            .await;
         }
     }
+
+    // Flush whatever is still queued so in-flight events are not lost
+    let mut events = pending;
+    drain_queued(&mut rx, &options.rules().filter, &mut events);
+    if !events.is_empty() {
+        let notification = render(&options, events);
+        deliver(
+            &notifiers,
+            &notification,
+            queue,
+            options.delivery_concurrency,
+        )
+        .await;
+    }
+
+    info!("Notification worker stopped");
 }
 
-async fn notification_worker(mut rx: Receiver<GameEvent>, notifier: TelegramNotifier) {
-    println!("Notification worker is started");
+/// Collects events and sends them as one summary per `digest_interval`.
+async fn digest_worker(
+    mut rx: Receiver<ServerEvent>,
+    notifiers: Vec<Box<dyn Notifier + Send + Sync>>,
+    options: WorkerOptions,
+    mut shutdown: Receiver<()>,
+) {
+    let interval = options.digest_interval;
+    info!(
+        interval_secs = interval.as_secs(),
+        "Sending notifications as digests"
+    );
+
+    let queue = options.queue.as_deref();
+    let mut queue_retry = tokio::time::interval(QUEUE_RETRY_INTERVAL);
+    let mut digest = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    let mut events = Vec::new();
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Ok(event) if options.rules().filter.allows(&event.event) => events.push(event),
+                Ok(_) => {}
+                Err(RecvError::Lagged(dropped)) => {
+                    warn!(
+                        dropped,
+                        "Notification worker fell behind, events were dropped"
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = digest.tick() => {
+                if !events.is_empty() {
+                    let notification = render_digest(&options, std::mem::take(&mut events));
+                    deliver(&notifiers, &notification, queue, options.delivery_concurrency).await;
+                }
+            }
+            _ = queue_retry.tick(), if queue.is_some() => retry_queued(&notifiers, queue).await,
+            _ = shutdown.recv() => break,
+        }
+    }
+
+    // Send what was collected so far rather than losing it
+    drain_queued(&mut rx, &options.rules().filter, &mut events);
+    if !events.is_empty() {
+        let notification = render_digest(&options, events);
+        deliver(
+            &notifiers,
+            &notification,
+            queue,
+            options.delivery_concurrency,
+        )
+        .await;
+    }
 
-    while let Ok(event) = rx.recv().await {
+    info!("Notification worker stopped");
+}
+
+/// Sums up joins and leaves per server as e.g. `In the last 15m: A and B
+/// joined, C left`, followed by the usual lines for every other event.
+fn render_digest(options: &WorkerOptions, events: Vec<ServerEvent>) -> Notification {
+    let rules = options.rules();
+    let mut roster: Vec<(String, Vec<String>, Vec<String>)> = Vec::new();
+    let mut others = Vec::new();
+    for mut event in events {
+        if let GameEvent::PlayerJoined(_) | GameEvent::PlayerLeft(_) = event.event {
+            rules.aliases.apply(&mut event.event);
+            event.event.escape_html();
+            event.server = escape_html(&event.server);
+        }
+        let (name, joined) = match event.event {
+            GameEvent::PlayerJoined(name) => (name, true),
+            GameEvent::PlayerLeft(name) => (name, false),
+            _ => {
+                others.push(event);
+                continue;
+            }
+        };
+        let index = match roster
+            .iter()
+            .position(|(server, ..)| *server == event.server)
+        {
+            Some(index) => index,
+            None => {
+                roster.push((event.server, Vec::new(), Vec::new()));
+                roster.len() - 1
+            }
+        };
+        let (_, joins, leaves) = &mut roster[index];
+        if joined {
+            joins.push(name);
+        } else {
+            leaves.push(name);
+        }
+    }
+
+    let templates = &rules.templates;
+    let mut lines = Vec::new();
+    for (server, joins, leaves) in roster {
+        let changes: Vec<String> = [(joins, "MSG_DIGEST_JOINED"), (leaves, "MSG_DIGEST_LEFT")]
+            .into_iter()
+            .filter(|(names, _)| !names.is_empty())
+            .map(|(names, key)| {
+                templates.message(
+                    key,
+                    &[("server", &server), ("players", &templates.list(&names))],
+                )
+            })
+            .collect();
+        let line = templates.message(
+            "MSG_DIGEST",
+            &[
+                ("server", &server),
+                ("duration", &format_duration(options.digest_interval)),
+                ("changes", &changes.join(", ")),
+            ],
+        );
+        lines.push(if options.prefix_server {
+            format!("[{}] {}", server, line)
+        } else {
+            line
+        });
+    }
+    if !others.is_empty() {
+        lines.push(render(options, others).text);
+    }
+
+    Notification {
+        text: lines.join("\n"),
+        player: None,
+        event_type: "digest".to_string(),
+    }
+}
+
+/// Renders what happened during the grace window after `server` restarted,
+/// summing up its joins as a single reconnect line.
+fn render_reconnects(
+    options: &WorkerOptions,
+    server: &str,
+    events: Vec<ServerEvent>,
+) -> Notification {
+    let (mut joins, others): (Vec<_>, Vec<_>) = events.into_iter().partition(|event| {
+        event.server == server && matches!(event.event, GameEvent::PlayerJoined(_))
+    });
+    if joins.is_empty() || others.is_empty() && joins.len() == 1 {
+        joins.extend(others);
+        return render(options, joins);
+    }
+
+    let rules = options.rules();
+    let names: Vec<String> = joins
+        .iter_mut()
+        .filter_map(|join| {
+            rules.aliases.apply(&mut join.event);
+            join.event.escape_html();
+            join.event.player().map(|name| format!("<b>{name}</b>"))
+        })
+        .collect();
+    let server = escape_html(server);
+    let line = rules.templates.message(
+        "MSG_RECONNECTED",
+        &[
+            ("server", &server),
+            ("count", &names.len().to_string()),
+            ("players", &rules.templates.list(&names)),
+        ],
+    );
+    let line = if options.prefix_server {
+        format!("[{}] {}", server, line)
+    } else {
+        line
+    };
+
+    if others.is_empty() {
+        return Notification {
+            text: line,
+            player: None,
+            event_type: "join".to_string(),
+        };
+    }
+    let rest = render(options, others);
+    Notification {
+        text: format!("{}\n{}", rest.text, line),
+        player: None,
+        event_type: "batch".to_string(),
+    }
+}
+
+/// Moves every event already waiting in the channel into `events`.
+fn drain_queued(
+    rx: &mut Receiver<ServerEvent>,
+    filter: &EventFilter,
+    events: &mut Vec<ServerEvent>,
+) {
+    loop {
+        match rx.try_recv() {
+            Ok(event) if filter.allows(&event.event) => events.push(event),
+            Ok(_) => {}
+            Err(TryRecvError::Lagged(dropped)) => {
+                warn!(
+                    dropped,
+                    "Notification worker fell behind, events were dropped"
+                );
+            }
+            Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+        }
+    }
+}
+
+/// Renders a batch of events into one notification, collapsing consecutive
+/// joins on the same server into a single line.
+fn render(options: &WorkerOptions, mut events: Vec<ServerEvent>) -> Notification {
+    let rules = options.rules();
+    let templates = &rules.templates;
+    let kind = events.first().map_or("batch", |first| first.event.kind());
+    let event_type = if events.iter().all(|e| e.event.kind() == kind) {
+        kind
+    } else {
+        "batch"
+    };
+    // Webhooks get the real name, only the text shows the alias
+    let player = match events.as_slice() {
+        [single] => single.event.player().map(str::to_string),
+        _ => None,
+    };
+    for event in &mut events {
+        rules.aliases.apply(&mut event.event);
+        event.event.escape_html();
+        event.server = escape_html(&event.server);
+    }
+
+    let mut events = events.into_iter().peekable();
+    let mut lines = Vec::new();
+    while let Some(ServerEvent {
+        server,
+        at,
+        online,
+        event,
+    }) = events.next()
+    {
+        let time = at
+            .with_timezone(&options.display_timezone)
+            .format("%H:%M")
+            .to_string();
+        let kind = event.kind();
+        let message = |key: &str, values: &[(&str, &str)]| {
+            let mut values = values.to_vec();
+            values.extend([("server", server.as_str()), ("time", time.as_str())]);
+            templates.message(key, &values)
+        };
         let message = match event {
             GameEvent::PlayerJoined(name) => {
-                format!("<b>{}</b> joined the game", name)
+                let mut names = vec![name];
+                while let Some(ServerEvent {
+                    event: GameEvent::PlayerJoined(name),
+                    ..
+                }) = events.next_if(|next| {
+                    next.server == server && matches!(next.event, GameEvent::PlayerJoined(_))
+                }) {
+                    names.push(name);
+                }
+                if names.len() == 1 {
+                    templates.join(&server, &names[0], &time, online)
+                } else {
+                    message(
+                        "MSG_JOIN_MANY",
+                        &[
+                            ("count", &names.len().to_string()),
+                            ("players", &names.join(", ")),
+                        ],
+                    )
+                }
+            }
+            GameEvent::PlayerLeft(name) => templates.leave(&server, &name, &time, online),
+            GameEvent::SessionReset { previous_session } => {
+                let reset = templates.session_reset(&server, &time);
+                match previous_session {
+                    Some(length) => format!(
+                        "{} {}",
+                        reset,
+                        message(
+                            "MSG_PREVIOUS_SESSION",
+                            &[("duration", &format_duration(length))]
+                        )
+                    ),
+                    None => reset,
+                }
             }
-            GameEvent::PlayerLeft(name) => {
-                format!("<b>{}</b> left the game", name)
+            GameEvent::ServerStarting => message("MSG_SERVER_STARTING", &[]),
+            GameEvent::MapLoaded => message("MSG_MAP_LOADED", &[]),
+            GameEvent::ChatMessage { author, text } => {
+                message("MSG_CHAT", &[("player", &author), ("text", &text)])
             }
-            GameEvent::SessionReset => "Server session restarted".to_string(),
+            GameEvent::PlayerDied { name, cause } => match cause {
+                Some(cause) => message("MSG_DEATH_CAUSE", &[("player", &name), ("cause", &cause)]),
+                None => message("MSG_DEATH", &[("player", &name)]),
+            },
+            GameEvent::PlayerKicked { name, reason } => match reason {
+                Some(reason) => {
+                    message("MSG_KICK_REASON", &[("player", &name), ("reason", &reason)])
+                }
+                None => message("MSG_KICK", &[("player", &name)]),
+            },
+            GameEvent::PlayerBanned { name, reason } => match reason {
+                Some(reason) => {
+                    message("MSG_BAN_REASON", &[("player", &name), ("reason", &reason)])
+                }
+                None => message("MSG_BAN", &[("player", &name)]),
+            },
+            GameEvent::BannedPlayerAttempt { name } => {
+                message("MSG_BAN_ATTEMPT", &[("player", &name)])
+            }
+            GameEvent::ResearchCompleted { technology } => {
+                message("MSG_RESEARCH", &[("technology", &technology)])
+            }
+            GameEvent::Achievement { name, achievement } => message(
+                "MSG_ACHIEVEMENT",
+                &[("player", &name), ("achievement", &achievement)],
+            ),
+            GameEvent::ServerUnresponsive { silent_for } => message(
+                "MSG_UNRESPONSIVE",
+                &[("duration", &format_duration(silent_for))],
+            ),
+            GameEvent::ServerRecovered => message("MSG_RECOVERED", &[]),
+            GameEvent::ServerBecameActive { name } => {
+                templates.server_active(&server, &name, &time)
+            }
+            GameEvent::ServerBecameEmpty { name } => templates.server_empty(&server, &name, &time),
+            GameEvent::NewPlayer(name) => message("MSG_NEW_PLAYER", &[("player", &name)]),
+            GameEvent::ReturningPlayer { name, away } => message(
+                "MSG_RETURNING",
+                &[
+                    ("player", &name),
+                    ("days", &(away.as_secs() / 86400).to_string()),
+                ],
+            ),
+            GameEvent::PlaytimeMilestone { name, hours } => message(
+                "MSG_MILESTONE",
+                &[("player", &name), ("hours", &hours.to_string())],
+            ),
+            GameEvent::Heartbeat {
+                players,
+                peak,
+                uptime,
+            } => templates.heartbeat(&server, &time, players, peak, &format_duration(uptime)),
         };
+        let message = templates.with_emoji(kind, message);
+        lines.push(if options.prefix_server {
+            format!("[{}] {}", server, message)
+        } else {
+            message
+        });
+    }
+    Notification {
+        text: lines.join("\n"),
+        player,
+        event_type: event_type.to_string(),
+    }
+}
 
-        println!("Notification: {}", &message);
-        notifier.notify(&message).await;
+/// Formats a duration as e.g. `2d 3h 15m`, dropping leading zero units.
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
     }
 }
 
-async fn sync_historical_state(state: &Arc<AppState>, log_path: &str) {
-    if !std::path::Path::new(log_path).exists() {
-        return; // Nothing to sync yet
+/// Writes buffered storage updates every `interval`, and once more at shutdown.
+async fn flush_storage(state: Arc<AppState>, interval: Duration) {
+    if state.storage.is_none() {
+        return;
+    }
+    let mut shutdown = state.shutdown.subscribe();
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+    loop {
+        let stopping = tokio::select! {
+            _ = ticker.tick() => false,
+            _ = shutdown.recv() => true,
+        };
+        if let Some(Err(e)) = state.with_storage(|storage| storage.flush()).await {
+            error!(error = %e, "Failed to write buffered events");
+        }
+        if stopping {
+            break;
+        }
     }
+}
+
+/// Periodically reports each server's player count and the dashboard's uptime.
+#[instrument(skip_all)]
+async fn heartbeat(state: Arc<AppState>, interval: Duration, skip_empty: bool) {
+    let mut shutdown = state.shutdown.subscribe();
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
 
-    println!("Reading history from file: {}", log_path);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.recv() => break,
+        }
 
-    let file = File::open(log_path).expect(&format!("Failed to read log file: {log_path}"));
-    let reader = BufReader::new(file);
+        let now = Utc::now();
+        let uptime = (now - state.started_at).to_std().unwrap_or_default();
+        for server in &state.servers {
+            let players = server.online_players.read().await.len();
+            if players == 0 && skip_empty {
+                continue;
+            }
+            let peak = server.peak_players.load(Ordering::Relaxed);
+            state.send(
+                server,
+                GameEvent::Heartbeat {
+                    players,
+                    peak,
+                    uptime,
+                },
+                now,
+            );
+        }
+    }
+}
 
-    let mut players = state.online_players.write().await;
+/// Reports servers whose log has been silent for `threshold` while players
+/// were online. `handle_line` clears the alert once lines arrive again.
+#[instrument(skip_all)]
+async fn watchdog(state: Arc<AppState>, threshold: Duration) {
+    let mut shutdown = state.shutdown.subscribe();
+    let mut ticker = tokio::time::interval(WATCHDOG_CHECK_INTERVAL.min(threshold));
 
-    for line in reader.lines() {
-        let content = line.expect("Failed to read content");
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.recv() => break,
+        }
 
-        if content.contains("Server Session Started") {
-            players.clear();
-            continue;
+        let now = Utc::now();
+        for server in &state.servers {
+            if server.online_players.read().await.is_empty() {
+                continue;
+            }
+            let last_line_at = server.last_line_at.read().await.unwrap_or(state.started_at);
+            let silent_for = (now - last_line_at).to_std().unwrap_or_default();
+            if silent_for >= threshold && !server.unresponsive.swap(true, Ordering::Relaxed) {
+                warn!(
+                    server = %server.name,
+                    silent_secs = silent_for.as_secs(),
+                    "Log went silent while players are online"
+                );
+                state.send(server, GameEvent::ServerUnresponsive { silent_for }, now);
+            }
+        }
+    }
+}
+
+/// How often the watchdog looks at each server's last log line.
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Copies every broadcast event except heartbeats into the history buffer.
+#[instrument(skip_all)]
+async fn record_history(state: Arc<AppState>, mut rx: Receiver<ServerEvent>) {
+    let mut shutdown = state.shutdown.subscribe();
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => event,
+            _ = shutdown.recv() => break,
+        };
+        match event {
+            Ok(event) if !matches!(event.event, GameEvent::Heartbeat { .. }) => {
+                state.history.push(event)
+            }
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => break,
         }
+    }
+}
+
+/// How often notifications queued after a failed delivery are retried.
+const QUEUE_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// Retries before a queued notification is given up on, a day's worth at
+/// [`QUEUE_RETRY_INTERVAL`].
+const QUEUE_MAX_ATTEMPTS: u32 = 1440;
 
-        let parts: Vec<&str> = content.split('|').map(|s| s.trim()).collect();
-        if parts.len() == 3 {
-            match parts[0] {
-                "JOIN" => {
-                    players.insert(parts[2].to_string());
+async fn deliver(
+    notifiers: &[Box<dyn Notifier + Send + Sync>],
+    notification: &Notification,
+    queue: Option<&dyn Storage>,
+    concurrency: usize,
+) {
+    info!(text = %notification.text, "Sending notification");
+    // A slow backend only holds up the others once `concurrency` are in flight
+    let accepting = notifiers.iter().filter(|n| n.accepts(notification));
+    stream::iter(accepting)
+        .for_each_concurrent(concurrency, |notifier| async move {
+            let text = notifier.format(notification);
+            let parts = match notifier.max_message_length() {
+                Some(limit) => split_message(&text, limit),
+                None => vec![text],
+            };
+            for text in parts {
+                // Queued as formatted, so a retry sends exactly what failed
+                let formatted = Notification {
+                    text,
+                    ..notification.clone()
+                };
+                let Err(e) = notifier.notify(&formatted).await else {
+                    continue;
+                };
+                error!(notifier = notifier.name(), error = %e, "Notification failed");
+                if !e.retryable {
+                    continue;
                 }
-                "LEAVE" => {
-                    players.remove(parts[2]);
+                if let Some(queue) = queue {
+                    match queue.queue_notification(&notifier.queue_key(), &formatted) {
+                        Ok(()) => {
+                            info!(notifier = notifier.name(), "Notification queued for retry")
+                        }
+                        Err(e) => error!(error = %e, "Failed to queue notification"),
+                    }
                 }
-                _ => {}
             }
+        })
+        .await;
+}
+
+/// Resends queued notifications in order. A backend that fails again keeps the
+/// rest of its queue for the next attempt, unless the notification was
+/// rejected outright or has used up its attempts, in which case it is dropped.
+async fn retry_queued(notifiers: &[Box<dyn Notifier + Send + Sync>], queue: Option<&dyn Storage>) {
+    let Some(queue) = queue else {
+        return;
+    };
+    let queued = match queue.queued_notifications() {
+        Ok(queued) => queued,
+        Err(e) => {
+            error!(error = %e, "Failed to read notification queue");
+            return;
+        }
+    };
+
+    let mut failing = HashSet::new();
+    for entry in queued {
+        if failing.contains(&entry.notifier) {
+            continue;
+        }
+        match notifiers.iter().find(|n| n.queue_key() == entry.notifier) {
+            Some(notifier) => match notifier.notify(&entry.notification).await {
+                Ok(()) => info!(notifier = notifier.name(), "Delivered queued notification"),
+                Err(e) if !e.retryable => error!(
+                    notifier = notifier.name(),
+                    error = %e,
+                    "Dropping queued notification the backend rejected"
+                ),
+                Err(e) if entry.attempts + 1 >= QUEUE_MAX_ATTEMPTS => error!(
+                    notifier = notifier.name(),
+                    error = %e,
+                    attempts = entry.attempts + 1,
+                    "Dropping queued notification after too many attempts"
+                ),
+                Err(e) => {
+                    warn!(notifier = notifier.name(), error = %e, "Queued notification still failing");
+                    failing.insert(entry.notifier.clone());
+                    if let Err(e) = queue.record_failed_attempt(entry.id) {
+                        error!(error = %e, "Failed to count queued notification attempt");
+                    }
+                    continue;
+                }
+            },
+            None => warn!(
+                notifier = %entry.notifier,
+                "Dropping queued notification for a notifier that is no longer configured"
+            ),
+        }
+        if let Err(e) = queue.remove_queued_notification(entry.id) {
+            error!(error = %e, "Failed to remove delivered notification from queue");
+        }
+    }
+}
+
+/// Rebuilds the roster from the log without notifying, reading only the first
+/// `until` bytes when given.
+async fn sync_historical_state(
+    state: &Arc<AppState>,
+    server: &ServerState,
+    log_path: &str,
+    until: Option<u64>,
+) -> std::io::Result<()> {
+    if !std::path::Path::new(log_path).exists() {
+        return Ok(()); // Nothing to sync yet
+    }
+
+    info!(log_path, "Reading history from file");
+
+    let file = File::open(log_path)?;
+    let reader = BufReader::new(file.take(until.unwrap_or(u64::MAX)));
+
+    let mut players = server.online_players.write().await;
+    let mut seen_players = server.seen_players.write().await;
+    let mut unknown_leaves = 0;
+
+    for line in lossy_lines(reader) {
+        let content = line?;
+        let (at, content) = state.patterns.split_timestamp(&content);
+
+        match state.patterns.parse_log_line(content) {
+            Some(LogEvent::SessionStarted) => {
+                players.clear();
+                server.reset_session_counters();
+                *server.session_started_at.write().await = at;
+            }
+            Some(LogEvent::Join(name)) => {
+                players
+                    .entry(name.to_string())
+                    .or_insert_with(|| at.unwrap_or_else(Utc::now));
+                seen_players.insert(name.to_string());
+                server
+                    .peak_players
+                    .fetch_max(players.len(), Ordering::Relaxed);
+            }
+            // The guard does the removal, so a known player needs nothing more
+            Some(LogEvent::Leave(name)) if players.remove(name).is_none() => unknown_leaves += 1,
+            Some(LogEvent::Leave(_)) | None => {}
+        }
+    }
+    if unknown_leaves > 0 {
+        debug!(
+            unknown_leaves,
+            "History has leaves of players who weren't online"
+        );
+    }
+
+    state
+        .metrics
+        .online_players(&server.name)
+        .set(players.len() as i64);
+    Ok(())
+}
+
+/// Replaces the log-derived roster with what the server itself reports over
+/// RCON, emitting join/leave events for every player the log got wrong.
+async fn reconcile_with_rcon(state: &AppState, server: &ServerState) {
+    let Some(rcon) = &server.rcon else {
+        return;
+    };
+
+    let online = match rcon.online_players().await {
+        Ok(online) => online,
+        Err(e) => {
+            warn!(error = %e, "Could not query online players over RCON, keeping log state");
+            return;
+        }
+    };
+
+    let known: Vec<String> = server.online_players.read().await.keys().cloned().collect();
+    let mut corrected = 0;
+    for name in known.iter().filter(|name| !online.contains(name)) {
+        state.remove_player(server, name, Utc::now()).await;
+        corrected += 1;
+    }
+    for name in online.iter().filter(|name| !known.contains(name)) {
+        state.add_player(server, name, Utc::now()).await;
+        corrected += 1;
+    }
+
+    if corrected > 0 {
+        info!(
+            players = online.len(),
+            corrected, "Reconciled online players over RCON"
+        );
+    } else {
+        debug!(players = online.len(), "Roster matches RCON");
+    }
+}
+
+/// Keeps reconciling every server that has RCON, so a join or leave the log
+/// didn't show, e.g. in a format the parser doesn't know, is still noticed.
+async fn poll_rcon(state: Arc<AppState>, interval: Duration) {
+    let mut shutdown = state.shutdown.subscribe();
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.recv() => break,
         }
+        for server in state.servers.iter().filter(|server| server.rcon.is_some()) {
+            reconcile_with_rcon(&state, server).await;
+        }
+    }
+}
+
+/// How often the log file is checked for rotation or truncation.
+const ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Identifies the log file on disk so rotations and truncations can be spotted.
+#[derive(Clone, Copy)]
+struct LogFileIdentity {
+    inode: u64,
+    len: u64,
+}
+
+impl LogFileIdentity {
+    fn read(log_path: &str) -> Option<Self> {
+        let metadata = std::fs::metadata(log_path).ok()?;
+        Some(Self {
+            inode: inode(&metadata),
+            len: metadata.len(),
+        })
     }
 }
 
+#[cfg(unix)]
+fn inode(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+/// Delays between attempts to follow the log again, doubling after each
+/// failure up to the cap.
+const LOG_RETRY_INITIAL: Duration = Duration::from_secs(1);
+const LOG_RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// Follows the server's log until shutdown. Whenever following fails the
+/// watcher starts over, history sync included, after a growing delay, so a
+/// misconfigured server doesn't take down the rest. Only errors that waiting
+/// can't fix end the watcher.
+#[instrument(skip_all, fields(server = %server.name))]
 async fn watch_log(
     app_state: Arc<AppState>,
-    log_path: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    sync_historical_state(&app_state, log_path).await;
+    server: Arc<ServerState>,
+    mut source: impl LogSource,
+) -> Result<(), WatchError> {
+    if server.reads_stdin() {
+        return watch_stdin(app_state, server).await;
+    }
+
+    let mut shutdown = app_state.shutdown.subscribe();
+    let mut backoff = LOG_RETRY_INITIAL;
+    let mut attempt = 0;
+    loop {
+        let Err(e) = follow_log(&app_state, &server, &mut source, &mut shutdown).await else {
+            break;
+        };
+        // A watcher that got going before failing earns a quick restart
+        if server.watcher_alive.swap(false, Ordering::Relaxed) {
+            backoff = LOG_RETRY_INITIAL;
+            attempt = 0;
+        }
+        if !e.is_retryable() {
+            return Err(e);
+        }
+        attempt += 1;
+        error!(
+            log_path = %server.log_path,
+            error = %e,
+            attempt,
+            retry_in_secs = backoff.as_secs(),
+            "Log monitor failed, restarting"
+        );
+        tokio::select! {
+            _ = sleep(backoff) => {}
+            _ = shutdown.recv() => break,
+        }
+        backoff = (backoff * 2).min(LOG_RETRY_MAX);
+    }
+
+    info!("Log monitor stopped");
+    Ok(())
+}
+
+async fn follow_log(
+    app_state: &Arc<AppState>,
+    server: &ServerState,
+    source: &mut impl LogSource,
+    shutdown: &mut Receiver<()>,
+) -> Result<(), WatchError> {
+    let log_path = server.log_path.as_str();
+    if Path::new(log_path).is_dir() {
+        return Err(WatchError::NotAFile {
+            path: log_path.to_string(),
+        });
+    }
 
-    let mut lines = MuxedLines::new()?;
-    lines
-        .add_file(log_path)
+    let resume_from = app_state.saved_offset(server, LogFileIdentity::read(log_path));
+    sync_historical_state(app_state, server, log_path, resume_from)
         .await
-        .expect(&format!("Failed to read log file: {log_path}"));
+        .map_err(|e| WatchError::io(log_path, e))?;
+    reconcile_with_rcon(app_state, server).await;
 
-    while !Path::new(log_path).exists() {
-        println!("Waiting for Factorio to create the log file...");
-        sleep(Duration::from_secs(2)).await;
+    // Taken before following starts, so lines appended in between are
+    // counted when they arrive rather than already being part of the length
+    let mut identity = LogFileIdentity::read(log_path);
+    tokio::select! {
+        result = source.follow(log_path) => result.map_err(WatchError::Linemux)?,
+        _ = shutdown.recv() => return Ok(()),
     }
-    println!("Log monitor started.");
+    info!("Log monitor started");
+    server.watcher_alive.store(true, Ordering::Relaxed);
 
-    while let Ok(Some(line)) = lines.next_line().await {
-        let content = line.line();
+    let offset = match resume_from {
+        Some(offset) => catch_up(app_state, server, log_path, offset)
+            .await
+            .map_err(|e| WatchError::io(log_path, e))?,
+        None => identity.map_or(0, |identity| identity.len),
+    };
+    let mut position = LogPosition::open(log_path, offset);
+    app_state.save_offset(server, identity, offset);
+    let mut rotation_check = tokio::time::interval(ROTATION_CHECK_INTERVAL);
+    let mut repeats = RepeatFilter::default();
 
-        if content.contains("Server Session Started") {
-            app_state.clear_active_players().await;
-            println!("Session reset detected. Cleared player list");
-            continue;
+    loop {
+        let line = tokio::select! {
+            line = source.next_line() => line,
+            _ = rotation_check.tick() => {
+                let current = LogFileIdentity::read(log_path);
+                if let (Some(previous), Some(current)) = (identity, current) {
+                    let rotated = current.inode != previous.inode;
+                    if rotated || current.len < previous.len {
+                        // Lines written since the swap may never have reached us, so
+                        // rebuild the roster from the file instead of replaying them
+                        let reason = if rotated { "rotation" } else { "truncation" };
+                        warn!(reason, "Log file replaced, resyncing");
+                        sync_historical_state(app_state, server, log_path, None)
+                            .await
+                            .map_err(|e| WatchError::io(log_path, e))?;
+                        source.follow(log_path).await.map_err(WatchError::Linemux)?;
+                        position = LogPosition::open(log_path, current.len);
+                        app_state.save_offset(server, Some(current), current.len);
+                    }
+                }
+                identity = current;
+                continue;
+            }
+            _ = shutdown.recv() => break,
+        };
+        let Some(line) = line.map_err(WatchError::Linemux)? else {
+            break;
+        };
+        if server.skip_repeated_lines && repeats.is_repeat(&line) {
+            debug!(line, "Skipping repeated log line");
+        } else {
+            handle_line(app_state, server, &line).await;
         }
+        let offset = position.advance(&line);
+        app_state.save_offset(server, identity, offset);
+    }
 
-        let parts: Vec<&str> = content.split('|').map(|s| s.trim()).collect();
+    Ok(())
+}
 
-        if parts.len() == 3 {
-            let action = parts[0];
-            let username = parts[2];
+/// Handles, with notifications, whatever was appended to the log after
+/// `offset` while the dashboard wasn't running. Returns the offset reached.
+async fn catch_up(
+    app_state: &AppState,
+    server: &ServerState,
+    log_path: &str,
+    mut offset: u64,
+) -> std::io::Result<u64> {
+    let mut file = File::open(log_path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
 
-            match action {
-                "JOIN" => {
-                    app_state.add_player(username).await;
-                }
-                "LEAVE" => {
-                    app_state.remove_player(username).await;
-                }
-                _ => {}
+    let mut line = Vec::new();
+    let mut caught_up = 0;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+        caught_up += 1;
+        handle_line(app_state, server, &lossy_line(&line)).await;
+    }
+
+    if caught_up > 0 {
+        info!(
+            lines = caught_up,
+            "Caught up on lines written while stopped"
+        );
+    }
+    Ok(offset)
+}
+
+/// Follows a log piped in on stdin. There is no file to replay history from.
+async fn watch_stdin(app_state: Arc<AppState>, server: Arc<ServerState>) -> Result<(), WatchError> {
+    let mut shutdown = app_state.shutdown.subscribe();
+    reconcile_with_rcon(&app_state, &server).await;
+
+    let mut lines = spawn_stdin_reader();
+    let mut repeats = RepeatFilter::default();
+    info!("Log monitor started on stdin");
+    server.watcher_alive.store(true, Ordering::Relaxed);
+
+    loop {
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            _ = shutdown.recv() => break,
+        };
+        match line {
+            Some(Ok(line)) if server.skip_repeated_lines && repeats.is_repeat(&line) => {
+                debug!(line, "Skipping repeated log line");
+            }
+            Some(Ok(line)) => handle_line(&app_state, &server, &line).await,
+            Some(Err(e)) => warn!(error = %e, "Failed to read line from stdin"),
+            None => {
+                info!("stdin closed");
+                break;
             }
         }
     }
 
+    info!("Log monitor stopped");
     Ok(())
 }
 
+/// Reads stdin on a plain thread: a blocking read can't be cancelled, and a
+/// detached thread doesn't hold up shutdown the way a runtime task would.
+fn spawn_stdin_reader() -> mpsc::Receiver<std::io::Result<String>> {
+    let (tx, rx) = mpsc::channel(100);
+    std::thread::spawn(move || {
+        for line in lossy_lines(std::io::stdin().lock()) {
+            if tx.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+async fn handle_line(app_state: &AppState, server: &ServerState, line: &str) {
+    let now = Utc::now();
+    *server.last_line_at.write().await = Some(now);
+    if server.unresponsive.swap(false, Ordering::Relaxed) {
+        info!(server = %server.name, "Log is active again");
+        app_state.send(server, GameEvent::ServerRecovered, now);
+    }
+    let (at, content) = app_state.patterns.split_timestamp(line);
+    let at = at.unwrap_or(now);
+
+    match app_state.patterns.classify(content) {
+        ParsedLine::ServerStarting => {
+            info!(server = %server.name, "Server is starting");
+            app_state.send(server, GameEvent::ServerStarting, at);
+        }
+        ParsedLine::MapLoaded => {
+            info!(server = %server.name, "Map loaded");
+            app_state.send(server, GameEvent::MapLoaded, at);
+        }
+        ParsedLine::SessionStarted => {
+            app_state.clear_active_players(server, at).await;
+            info!("Session reset detected. Cleared player list");
+        }
+        ParsedLine::Chat { author, text } => app_state.relay_chat(server, &author, &text, at),
+        ParsedLine::Death { player, cause } => app_state.report_death(server, player, cause, at),
+        ParsedLine::Kick { player, reason } => app_state.report_kick(server, player, reason, at),
+        ParsedLine::Ban { player, reason } => app_state.report_ban(server, player, reason, at),
+        ParsedLine::BanAttempt { player } => app_state.report_ban_attempt(server, player, at),
+        ParsedLine::Research { technology } => app_state.report_research(server, technology, at),
+        ParsedLine::Achievement {
+            player,
+            achievement,
+        } => app_state.report_achievement(server, player, achievement, at),
+        ParsedLine::Join { player } => app_state.add_player(server, &player, at).await,
+        ParsedLine::Leave { player } => app_state.remove_player(server, &player, at).await,
+        ParsedLine::Unrecognized => {}
+    }
+}
+
+/// Names a server after its log file when `SERVER_NAMES` isn't set.
+fn server_name_from_path(log_path: &str) -> String {
+    if log_path == STDIN_LOG_PATH {
+        return "stdin".to_string();
+    }
+    Path::new(log_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| log_path.to_string())
+}
+
+/// Prints what a log file, or a directory of them, contains and exits, for
+/// `--replay <path>`.
+fn replay_and_exit(log_path: &str, config_path: Option<&str>) -> ! {
+    let patterns = config_path
+        .map(Settings::from_file)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .and_then(|settings| LogPatterns::load(&settings).map_err(|problems| problems.join(", ")));
+    let result = match patterns {
+        Ok(patterns) => replay::run(log_path, &patterns).map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        error!(log_path, error = %e, "Replay failed");
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+/// Sends a test message through every notifier, reports how each fared and
+/// exits, non-zero if any failed, for `--test-notifiers`.
+async fn test_notifiers_and_exit(notifiers: &[Box<dyn Notifier + Send + Sync>]) -> ! {
+    if notifiers.is_empty() {
+        error!("No notifiers are configured");
+        std::process::exit(1);
+    }
+    let notification = Notification {
+        text: "Dashboard test message".to_string(),
+        player: None,
+        event_type: "test".to_string(),
+    };
+    let mut failed = 0;
+    for notifier in notifiers {
+        let formatted = Notification {
+            text: notifier.format(&notification),
+            ..notification.clone()
+        };
+        match notifier.notify(&formatted).await {
+            Ok(()) => info!(notifier = %notifier.queue_key(), "Test message sent"),
+            Err(e) => {
+                error!(notifier = %notifier.queue_key(), error = %e, "Test message failed");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        error!(failed, total = notifiers.len(), "Some notifiers failed");
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM as sent by Docker and systemd on stop.
+#[cfg(unix)]
+async fn shutdown_signal() -> &'static str {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result.expect("Failed to listen for Ctrl-C");
+            "SIGINT"
+        }
+        _ = terminate.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> &'static str {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for Ctrl-C");
+    "Ctrl-C"
+}
+
+/// Top-level keys of [`Config::sanitized`] that [`NotifyRules`] is made from.
+const RELOADABLE_SETTINGS: &[&str] = &[
+    "templates",
+    "notify_on",
+    "notify_ignore_players",
+    "notify_only_players",
+    "chat_notify_keywords",
+    "player_aliases",
+];
+
+/// Reads the configuration again on every SIGHUP and swaps in the new
+/// [`NotifyRules`]. Anything else that changed is only logged, as it takes a
+/// restart; an invalid configuration leaves the current one in place.
+#[cfg(unix)]
+async fn reload_on_sighup(
+    cli: Cli,
+    rules: Arc<std::sync::RwLock<Arc<NotifyRules>>>,
+    running: serde_json::Value,
+    mut shutdown: Receiver<()>,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!(error = %e, "Failed to install SIGHUP handler, config reloads are disabled");
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => {}
+            _ = shutdown.recv() => break,
+        }
+        let config = match Config::load(&cli) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{e}");
+                warn!("Keeping the current configuration");
+                continue;
+            }
+        };
+        let reloaded = config.sanitized();
+        if let (Some(running), Some(reloaded)) = (running.as_object(), reloaded.as_object()) {
+            for (key, value) in reloaded {
+                if !RELOADABLE_SETTINGS.contains(&key.as_str()) && running.get(key) != Some(value) {
+                    warn!(setting = %key, "Changed setting only takes effect after a restart");
+                }
+            }
+        }
+        *rules.write().unwrap() = Arc::new(NotifyRules::new(&config));
+        info!("Configuration reloaded");
+    }
+}
+
+fn init_tracing() {
+    let builder = tracing_subscriber::fmt().with_env_filter(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    );
+
+    match optional_env("LOG_FORMAT").as_deref() {
+        Some("json") => builder.json().init(),
+        Some("text") | None => builder.init(),
+        Some(other) => {
+            builder.init();
+            warn!(format = other, "Unknown LOG_FORMAT, falling back to text");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
+    init_tracing();
 
-    let (tx, rx) = tokio::sync::broadcast::channel::<GameEvent>(100);
-    let app_state = Arc::new(AppState::new(tx));
+    let cli = Cli::parse();
+    if let Some(log_path) = &cli.replay {
+        replay_and_exit(log_path, cli.config_path().as_deref());
+    }
+    let demo = cli.demo;
+    let config = Config::load(&cli).unwrap_or_else(|e| {
+        error!("{e}");
+        std::process::exit(1);
+    });
+    let sanitized_config = config.sanitized();
+    let rules = Arc::new(std::sync::RwLock::new(Arc::new(NotifyRules::new(&config))));
 
-    let factorio_log_path =
-        env::var("FACTORIO_LOG_PATH").expect("FACTORIO_LOG_PATH env var is required");
-    let telegram_token = env::var("TELEGRAM_TOKEN").expect("TELEGRAM_TOKEN env var is required");
-    let telegram_chat_id =
-        env::var("TELEGRAM_CHAT_ID").expect("TELEGAM_CHAT_ID env var is required");
+    let client = http_client(config.http_timeout).unwrap_or_else(|e| {
+        error!(error = %e, "Failed to set up the HTTP client");
+        std::process::exit(1);
+    });
+    let mut notifiers: Vec<Box<dyn Notifier + Send + Sync>> = Vec::new();
+    if let Some(telegram) = config.telegram {
+        for chat in telegram.chats {
+            notifiers.push(Box::new(TelegramNotifier::new(
+                client.clone(),
+                telegram.token.clone(),
+                chat,
+                telegram.max_retries,
+            )));
+        }
+    }
+    if let Some(webhook_url) = config.discord_webhook_url {
+        notifiers.push(Box::new(DiscordNotifier::new(client.clone(), webhook_url)));
+    }
+    if let Some(webhook_url) = config.slack_webhook_url {
+        notifiers.push(Box::new(SlackNotifier::new(client.clone(), webhook_url)));
+    }
+    if let Some(matrix) = config.matrix {
+        notifiers.push(Box::new(MatrixNotifier::new(
+            client.clone(),
+            matrix.homeserver,
+            matrix.token,
+            matrix.room_id,
+        )));
+    }
+    if let Some(webhook) = config.webhook {
+        notifiers.push(Box::new(WebhookNotifier::new(
+            client.clone(),
+            webhook.url,
+            webhook.template,
+            webhook.headers,
+        )));
+    }
+    if config.dry_run {
+        warn!("Dry run enabled, notifications will only be logged");
+        notifiers = notifiers
+            .into_iter()
+            .map(|notifier| {
+                Box::new(DryRunNotifier::new(notifier)) as Box<dyn Notifier + Send + Sync>
+            })
+            .collect();
+        if notifiers.is_empty() {
+            notifiers.push(Box::new(DryRunNotifier::without_backend()));
+        }
+    }
+    if cli.test_notifiers {
+        test_notifiers_and_exit(&notifiers).await;
+    }
+
+    let storage: Option<Arc<dyn Storage>> = match config.storage_backend {
+        StorageBackend::Sqlite => config.db_path.as_deref().map(|db_path| {
+            Arc::new(
+                SqliteStorage::open(
+                    db_path,
+                    config.db_flush_events,
+                    config.min_session,
+                    config.milestone_hours,
+                    config.returning_after,
+                )
+                .unwrap_or_else(|e| {
+                    error!(db_path, error = %e, "Failed to open database");
+                    std::process::exit(1);
+                }),
+            ) as Arc<dyn Storage>
+        }),
+        StorageBackend::Memory => Some(Arc::new(MemoryStorage)),
+    };
+    // A dry run never fails to deliver, and mustn't drain a real run's queue.
+    // The memory backend would only drop what it was given.
+    let queue = if config.dry_run || matches!(config.storage_backend, StorageBackend::Memory) {
+        None
+    } else {
+        storage.clone()
+    };
+
+    // RCON settings describe a single server, so they belong to the first one watched
+    let rcon_poll_interval = config
+        .rcon
+        .as_ref()
+        .map_or(Duration::ZERO, |rcon| rcon.poll_interval);
+    let mut rcon = config
+        .rcon
+        .map(|rcon| RconClient::new(rcon.address, rcon.password));
+    let servers: Vec<Arc<ServerState>> = config
+        .server_names
+        .into_iter()
+        .zip(config.log_paths)
+        .map(|(name, log_path)| {
+            Arc::new(ServerState {
+                skip_repeated_lines: config.skip_repeated_lines,
+                notify_unknown_leaves: config.notify_unknown_leaves,
+                ..ServerState::new(name, log_path, rcon.take())
+            })
+        })
+        .collect();
+    let prefix_server = servers.len() > 1;
+
+    let (tx, rx) = tokio::sync::broadcast::channel::<ServerEvent>(config.event_channel_capacity);
+    let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    let app_state = Arc::new(AppState::new(
+        servers,
+        tx,
+        shutdown_tx,
+        storage,
+        config.debounce_window,
+        config.join_grace,
+        config.patterns,
+        config.history_size,
+    ));
 
-    tokio::spawn(async move {
-        if let Err(e) = watch_log(Arc::clone(&app_state), &factorio_log_path).await {
-            eprintln!("Log monitor error: {}", e);
+    let http_config = http::HttpConfig {
+        bind_addr: config.http_bind_addr,
+        command_secret: config.command_secret,
+        display_timezone: config.display_timezone,
+        tls: config.tls,
+        basic_auth: config.basic_auth,
+        access_log: config.http_access_log,
+        access_log_per_minute: config.http_access_log_per_minute,
+        sanitized_config: sanitized_config.clone(),
+    };
+
+    let mut tasks = Vec::new();
+    tasks.push(tokio::spawn(record_history(
+        Arc::clone(&app_state),
+        app_state.tx.subscribe(),
+    )));
+    for server in &app_state.servers {
+        let watcher_state = Arc::clone(&app_state);
+        let server = Arc::clone(server);
+        if demo {
+            tasks.push(tokio::spawn(demo::run(watcher_state, server)));
+            continue;
         }
-    });
+        tasks.push(tokio::spawn(async move {
+            let source = FileLogSource::default();
+            if let Err(e) = watch_log(watcher_state, Arc::clone(&server), source).await {
+                error!(server = %server.name, error = %e, "Log monitor error");
+            }
+            server.watcher_alive.store(false, Ordering::Relaxed);
+        }));
+    }
 
-    let notifier = TelegramNotifier::new(telegram_token, telegram_chat_id);
-    tokio::spawn(notification_worker(rx, notifier));
+    let http_state = Arc::clone(&app_state);
+    tasks.push(tokio::spawn(async move {
+        if let Err(e) = http::serve(http_state, http_config).await {
+            error!(error = %e, "HTTP server error");
+        }
+    }));
 
-    let result: Result<(), std::io::Error> = tokio::signal::ctrl_c().await;
-    result.unwrap();
+    if !config.unresponsive_after.is_zero() {
+        tasks.push(tokio::spawn(watchdog(
+            Arc::clone(&app_state),
+            config.unresponsive_after,
+        )));
+    }
+    if !config.db_flush_interval.is_zero() {
+        tasks.push(tokio::spawn(flush_storage(
+            Arc::clone(&app_state),
+            config.db_flush_interval,
+        )));
+    }
+    if !rcon_poll_interval.is_zero() {
+        tasks.push(tokio::spawn(poll_rcon(
+            Arc::clone(&app_state),
+            rcon_poll_interval,
+        )));
+    }
+    if !config.heartbeat_interval.is_zero() {
+        tasks.push(tokio::spawn(heartbeat(
+            Arc::clone(&app_state),
+            config.heartbeat_interval,
+            config.skip_empty_heartbeat,
+        )));
+    }
+
+    #[cfg(unix)]
+    tasks.push(tokio::spawn(reload_on_sighup(
+        cli,
+        Arc::clone(&rules),
+        sanitized_config,
+        app_state.shutdown.subscribe(),
+    )));
+
+    let worker_options = WorkerOptions {
+        rules,
+        prefix_server,
+        limiter: RateLimiter::per_minute(config.notify_rate_per_minute),
+        join_batch_window: config.join_batch_window,
+        reconnect_grace: config.reconnect_grace,
+        delivery_concurrency: config.notify_concurrency,
+        digest_interval: config.digest_interval,
+        display_timezone: config.display_timezone,
+        queue,
+    };
+    // Without notifiers the dashboard still serves the web UI and metrics
+    let worker = if notifiers.is_empty() {
+        warn!(
+            "No notifier configured, notifications are disabled. Set TELEGRAM_TOKEN, \
+             DISCORD_WEBHOOK_URL, SLACK_WEBHOOK_URL, MATRIX_HOMESERVER or WEBHOOK_URL to enable them"
+        );
+        None
+    } else {
+        Some(tokio::spawn(notification_worker(
+            rx,
+            notifiers,
+            worker_options,
+            app_state.shutdown.subscribe(),
+        )))
+    };
 
-    println!("Shutting down log monitor");
+    let signal = shutdown_signal().await;
+
+    info!(signal, "Shutting down log monitor");
+    let _ = app_state.shutdown.send(());
+
+    // Stop producers first so the worker can flush every queued notification
+    for task in tasks {
+        let _ = task.await;
+    }
+    if let Some(worker) = worker {
+        let _ = worker.await;
+    }
+    if let Some(storage) = &app_state.storage
+        && let Err(e) = storage.flush()
+    {
+        error!(error = %e, "Failed to write buffered events");
+    }
 }
@@ -0,0 +1,71 @@
+use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+
+/// Prometheus metrics describing server population over time, labelled by server.
+pub struct Metrics {
+    registry: Registry,
+    online_players: IntGaugeVec,
+    joins: IntCounterVec,
+    leaves: IntCounterVec,
+    session_resets: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let online_players = IntGaugeVec::new(
+            Opts::new("factorio_online_players", "Players currently online"),
+            &["server"],
+        )
+        .unwrap();
+        let joins = IntCounterVec::new(
+            Opts::new("factorio_player_joins_total", "Player join events"),
+            &["server"],
+        )
+        .unwrap();
+        let leaves = IntCounterVec::new(
+            Opts::new("factorio_player_leaves_total", "Player leave events"),
+            &["server"],
+        )
+        .unwrap();
+        let session_resets = IntCounterVec::new(
+            Opts::new("factorio_session_resets_total", "Server session restarts"),
+            &["server"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(online_players.clone())).unwrap();
+        registry.register(Box::new(joins.clone())).unwrap();
+        registry.register(Box::new(leaves.clone())).unwrap();
+        registry.register(Box::new(session_resets.clone())).unwrap();
+
+        Self {
+            registry,
+            online_players,
+            joins,
+            leaves,
+            session_resets,
+        }
+    }
+
+    pub fn online_players(&self, server: &str) -> IntGauge {
+        self.online_players.with_label_values(&[server])
+    }
+
+    pub fn joins(&self, server: &str) -> IntCounter {
+        self.joins.with_label_values(&[server])
+    }
+
+    pub fn leaves(&self, server: &str) -> IntCounter {
+        self.leaves.with_label_values(&[server])
+    }
+
+    pub fn session_resets(&self, server: &str) -> IntCounter {
+        self.session_resets.with_label_values(&[server])
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> prometheus::Result<String> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
@@ -0,0 +1,321 @@
+mod discord;
+mod dry_run;
+mod matrix;
+mod slack;
+mod telegram;
+mod webhook;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use thiserror::Error;
+
+pub use discord::DiscordNotifier;
+pub use dry_run::DryRunNotifier;
+pub use matrix::MatrixNotifier;
+pub use slack::SlackNotifier;
+pub use telegram::TelegramNotifier;
+pub use webhook::{DEFAULT_WEBHOOK_TEMPLATE, WebhookNotifier, parse_header, render_body};
+
+/// The client every notifier sends with. A zero `timeout` means none, which
+/// lets a hung endpoint stall the notification worker.
+pub fn http_client(timeout: Duration) -> reqwest::Result<Client> {
+    let mut builder = Client::builder().user_agent(concat!(
+        env!("CARGO_PKG_NAME"),
+        "/",
+        env!("CARGO_PKG_VERSION")
+    ));
+    if !timeout.is_zero() {
+        builder = builder.timeout(timeout);
+    }
+    builder.build()
+}
+
+/// Escapes `&`, `<` and `>` so a value can't break or inject markup in the
+/// HTML that notification texts are written in.
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Reverses [`escape_html`], for backends that send plain text.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Splits `text` into messages of at most `limit` UTF-16 code units, which is
+/// how Telegram counts and never less than Discord's count. Breaks fall between
+/// lines, so batches and digests split cleanly; a line too long on its own is
+/// cut short with an ellipsis.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    if utf16_len(text) <= limit {
+        return vec![text.to_string()];
+    }
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in text.split('\n') {
+        let line = truncate_line(line, limit);
+        if !current.is_empty() && utf16_len(&current) + 1 + utf16_len(&line) > limit {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Cuts `line` down to `limit` including the ellipsis, without leaving half a
+/// tag or entity behind, and closes any bold the cut falls inside.
+fn truncate_line(line: &str, limit: usize) -> String {
+    if utf16_len(line) <= limit {
+        return line.to_string();
+    }
+    let budget = limit.saturating_sub(1);
+    let cut = cut_line(line, budget);
+    let close = unclosed_bold(cut);
+    if close.is_empty() {
+        return format!("{cut}…");
+    }
+    // Make room for the closing markup
+    let cut = cut_line(line, budget.saturating_sub(close.len()));
+    format!("{cut}{}…", unclosed_bold(cut))
+}
+
+/// The longest start of `line` within `budget` UTF-16 code units that doesn't
+/// end partway through a tag, an entity or a Markdown `**`.
+fn cut_line(line: &str, budget: usize) -> &str {
+    let mut used = 0;
+    let mut end = 0;
+    for (index, c) in line.char_indices() {
+        used += c.len_utf16();
+        if used > budget {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    let mut cut = &line[..end];
+    if let Some(open) = cut.rfind(['<', '&'])
+        && !cut[open..].contains(['>', ';'])
+    {
+        cut = &cut[..open];
+    }
+    if cut.ends_with('*') && line[cut.len()..].starts_with('*') {
+        cut = &cut[..cut.len() - 1];
+    }
+    // Bold opened right at the end would close around nothing
+    let opener = match unclosed_bold(cut) {
+        "</b>" => "<b>",
+        close => close,
+    };
+    cut.strip_suffix(opener).unwrap_or(cut)
+}
+
+/// The markup closing a bold left open in `text`, whether still in HTML or
+/// already turned into Markdown.
+fn unclosed_bold(text: &str) -> &'static str {
+    if text.matches("<b>").count() > text.matches("</b>").count() {
+        "</b>"
+    } else if text.matches("**").count() % 2 == 1 {
+        "**"
+    } else {
+        ""
+    }
+}
+
+/// A rendered message along with what it is about.
+#[derive(Clone)]
+pub struct Notification {
+    pub text: String,
+    /// Set when the message concerns a single player.
+    pub player: Option<String>,
+    /// The kind of event, e.g. `join`, or `batch` when several kinds were merged.
+    pub event_type: String,
+}
+
+impl Notification {
+    /// The text with `<b>` tags swapped for `bold` and entities decoded, for
+    /// backends that don't render HTML.
+    fn plain_text(&self, bold: &str) -> String {
+        unescape_html(&self.text.replace("<b>", bold).replace("</b>", bold))
+    }
+}
+
+/// Why a notification couldn't be delivered.
+#[derive(Debug, Error)]
+#[error("{reason}")]
+pub struct NotifyError {
+    pub reason: String,
+    /// Whether sending the same notification again later could work. A
+    /// rejected message or a deleted webhook never will.
+    pub retryable: bool,
+}
+
+impl NotifyError {
+    /// A failure that may clear up on its own, such as a dropped connection.
+    pub fn transient(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            retryable: true,
+        }
+    }
+
+    /// A failure that sending again won't fix.
+    pub fn permanent(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            retryable: false,
+        }
+    }
+
+    /// A failure the backend answered with `status`. Only rate limits, timeouts
+    /// and server errors are worth retrying.
+    pub fn from_status(status: StatusCode, reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            retryable: is_retryable(status),
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// A destination that game event messages are delivered to.
+#[async_trait]
+pub trait Notifier {
+    /// Short backend name used in logs, e.g. `telegram`.
+    fn name(&self) -> &'static str;
+
+    /// Renders the notification in this backend's own markup. Texts are
+    /// written in HTML with `<b>` for bold, which is passed through as is
+    /// unless overridden. Templates and translations have been applied by
+    /// then, so a backend only has the markup to convert.
+    fn format(&self, notification: &Notification) -> String {
+        notification.text.clone()
+    }
+
+    /// Identifies this destination in the retry queue. Backends that can be
+    /// configured more than once must tell their instances apart.
+    fn queue_key(&self) -> String {
+        self.name().to_string()
+    }
+
+    /// The longest text, in UTF-16 code units, this backend takes in one
+    /// message. Longer ones are split with [`split_message`].
+    fn max_message_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether this destination wants the notification at all.
+    fn accepts(&self, _notification: &Notification) -> bool {
+        true
+    }
+
+    /// Delivers the notification, whose text [`Notifier::format`] has already
+    /// rendered, describing what went wrong if it couldn't.
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(text: &str) -> Notification {
+        Notification {
+            text: text.to_string(),
+            player: None,
+            event_type: "join".to_string(),
+        }
+    }
+
+    #[test]
+    fn escapes_angle_brackets() {
+        assert_eq!(escape_html("<b>Mallory</b>"), "&lt;b&gt;Mallory&lt;/b&gt;");
+    }
+
+    #[test]
+    fn escapes_ampersands_first() {
+        assert_eq!(
+            escape_html("Tom & Jerry &lt;3"),
+            "Tom &amp; Jerry &amp;lt;3"
+        );
+    }
+
+    #[test]
+    fn plain_text_restores_escaped_names() {
+        let text = format!("<b>{}</b> joined the game", escape_html("<Tom & Jerry>"));
+        assert_eq!(
+            notification(&text).plain_text("**"),
+            "**<Tom & Jerry>** joined the game"
+        );
+    }
+
+    #[test]
+    fn short_messages_are_left_alone() {
+        assert_eq!(split_message("a\nb", 10), ["a\nb"]);
+    }
+
+    #[test]
+    fn splits_between_lines() {
+        assert_eq!(
+            split_message("first line\nsecond\nthird", 13),
+            ["first line", "second\nthird"]
+        );
+    }
+
+    #[test]
+    fn truncates_overlong_lines_outside_entities() {
+        assert_eq!(
+            split_message("<b>Bob</b>: a &amp; b", 16),
+            ["<b>Bob</b>: a …"]
+        );
+    }
+
+    #[test]
+    fn closes_bold_cut_short() {
+        assert_eq!(split_message("<b>Alice and Bob</b>", 12), ["<b>Alic</b>…"]);
+        assert_eq!(split_message("**Alice and Bob**", 10), ["**Alice**…"]);
+    }
+
+    #[test]
+    fn never_cuts_markdown_bold_in_half() {
+        assert_eq!(split_message("**Al** and **Bob**", 13), ["**Al** and …"]);
+        assert_eq!(split_message("**Al** and **Bob**", 14), ["**Al** and …"]);
+    }
+
+    #[test]
+    fn counts_in_utf16_code_units() {
+        assert_eq!(split_message("✅🔄🔄", 4), ["✅🔄…"]);
+    }
+
+    #[test]
+    fn only_retries_statuses_that_can_clear_up() {
+        assert!(NotifyError::from_status(StatusCode::TOO_MANY_REQUESTS, "").retryable);
+        assert!(NotifyError::from_status(StatusCode::BAD_GATEWAY, "").retryable);
+        assert!(!NotifyError::from_status(StatusCode::BAD_REQUEST, "").retryable);
+        assert!(!NotifyError::from_status(StatusCode::NOT_FOUND, "").retryable);
+    }
+
+    #[test]
+    fn plain_text_keeps_literal_entities_in_names() {
+        let text = format!("<b>{}</b>", escape_html("&lt;"));
+        assert_eq!(notification(&text).plain_text(""), "&lt;");
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Serialize;
+
+use super::{Notification, Notifier, NotifyError};
+
+/// Webhooks refuse `content` longer than this.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(Serialize)]
+struct DiscordPayload {
+    content: String,
+    allowed_mentions: AllowedMentions,
+}
+
+/// Which mentions in `content` Discord turns into pings. Player names and chat
+/// come straight from the game, so none are.
+#[derive(Serialize)]
+struct AllowedMentions {
+    parse: [&'static str; 0],
+}
+
+impl DiscordPayload {
+    fn new(content: String) -> Self {
+        Self {
+            content,
+            allowed_mentions: AllowedMentions { parse: [] },
+        }
+    }
+}
+
+pub struct DiscordNotifier {
+    webhook_url: String,
+    client: Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(client: Client, webhook_url: String) -> Self {
+        Self {
+            webhook_url,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    /// Discord doesn't render HTML, so the bold tags become Markdown.
+    fn format(&self, notification: &Notification) -> String {
+        notification.plain_text("**")
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        Some(MAX_MESSAGE_LENGTH)
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let payload = DiscordPayload::new(notification.text.clone());
+
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(&payload)
+            .send()
+            .await;
+        let res =
+            response.map_err(|e| NotifyError::transient(format!("HTTP Request Error: {e}")))?;
+        let status = res.status();
+        if !status.is_success() {
+            let err_body = res.text().await.unwrap_or_default();
+            return Err(NotifyError::from_status(
+                status,
+                format!("Discord API Error: {err_body}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_disables_mentions() {
+        let payload = DiscordPayload::new("@everyone joined the game".to_string());
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "content": "@everyone joined the game",
+                "allowed_mentions": { "parse": [] },
+            })
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Notification, Notifier, NotifyError};
+
+/// Stands in for a configured notifier and only logs what it would have sent,
+/// formatted the way that notifier would.
+pub struct DryRunNotifier {
+    backend: &'static str,
+    inner: Option<Box<dyn Notifier + Send + Sync>>,
+}
+
+impl DryRunNotifier {
+    pub fn new(inner: Box<dyn Notifier + Send + Sync>) -> Self {
+        Self {
+            backend: inner.name(),
+            inner: Some(inner),
+        }
+    }
+
+    /// Logs notifications when no backend is configured at all.
+    pub fn without_backend() -> Self {
+        Self {
+            backend: "none",
+            inner: None,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DryRunNotifier {
+    fn name(&self) -> &'static str {
+        self.backend
+    }
+
+    fn format(&self, notification: &Notification) -> String {
+        match &self.inner {
+            Some(inner) => inner.format(notification),
+            None => notification.text.clone(),
+        }
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        self.inner
+            .as_ref()
+            .and_then(|inner| inner.max_message_length())
+    }
+
+    fn accepts(&self, notification: &Notification) -> bool {
+        self.inner
+            .as_ref()
+            .is_none_or(|inner| inner.accepts(notification))
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let message = &notification.text;
+        info!(backend = self.backend, text = %message, "Dry run, not sending notification");
+        Ok(())
+    }
+}
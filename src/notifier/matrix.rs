@@ -0,0 +1,110 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::Serialize;
+
+use super::{Notification, Notifier, NotifyError};
+
+#[derive(Serialize)]
+struct MatrixMessage {
+    msgtype: &'static str,
+    body: String,
+    format: &'static str,
+    formatted_body: String,
+}
+
+pub struct MatrixNotifier {
+    homeserver: String,
+    token: String,
+    room_id: String,
+    client: Client,
+    // Transaction IDs must be unique per access token, so combine the start
+    // time with a counter to stay unique across restarts too
+    txn_prefix: u128,
+    txn_counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    pub fn new(client: Client, homeserver: String, token: String, room_id: String) -> Self {
+        let txn_prefix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        Self {
+            homeserver,
+            token,
+            room_id,
+            client,
+            txn_prefix,
+            txn_counter: AtomicU64::new(0),
+        }
+    }
+
+    fn send_url(&self) -> Option<Url> {
+        let txn_id = format!(
+            "{}-{}",
+            self.txn_prefix,
+            self.txn_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut url = Url::parse(&self.homeserver).ok()?;
+        // Pushed segments are escaped, so odd characters in the room ID stay in one segment
+        url.path_segments_mut().ok()?.pop_if_empty().extend([
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            &self.room_id,
+            "send",
+            "m.room.message",
+            &txn_id,
+        ]);
+        Some(url)
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let message = &notification.text;
+        let url = self.send_url().ok_or_else(|| {
+            NotifyError::permanent(format!(
+                "Invalid Matrix homeserver URL: {}",
+                self.homeserver
+            ))
+        })?;
+        // Clients without HTML support fall back to `body`
+        let payload = MatrixMessage {
+            msgtype: "m.text",
+            body: notification.plain_text(""),
+            format: "org.matrix.custom.html",
+            formatted_body: message.to_string(),
+        };
+
+        let response = self
+            .client
+            .put(url)
+            .bearer_auth(&self.token)
+            .json(&payload)
+            .send()
+            .await;
+        let res =
+            response.map_err(|e| NotifyError::transient(format!("HTTP Request Error: {e}")))?;
+        let status = res.status();
+        if !status.is_success() {
+            let err_body = res.text().await.unwrap_or_default();
+            return Err(NotifyError::from_status(
+                status,
+                format!("Matrix API Error: {err_body}"),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Serialize;
+
+use super::{Notification, Notifier, NotifyError};
+
+#[derive(Serialize)]
+struct SlackPayload {
+    text: String,
+}
+
+pub struct SlackNotifier {
+    webhook_url: String,
+    client: Client,
+}
+
+impl SlackNotifier {
+    pub fn new(client: Client, webhook_url: String) -> Self {
+        Self {
+            webhook_url,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    /// Slack uses its own mrkdwn, where `*` is bold. It escapes `&`, `<` and
+    /// `>` the same way HTML does, so the text is otherwise already safe.
+    fn format(&self, notification: &Notification) -> String {
+        notification.text.replace("<b>", "*").replace("</b>", "*")
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let payload = SlackPayload {
+            text: notification.text.clone(),
+        };
+
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(&payload)
+            .send()
+            .await;
+        let res =
+            response.map_err(|e| NotifyError::transient(format!("HTTP Request Error: {e}")))?;
+        let status = res.status();
+        if !status.is_success() {
+            let err_body = res.text().await.unwrap_or_default();
+            return Err(NotifyError::from_status(
+                status,
+                format!("Slack API Error: {err_body}"),
+            ));
+        }
+        Ok(())
+    }
+}
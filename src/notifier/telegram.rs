@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use tracing::warn;
+
+use super::{Notification, Notifier, NotifyError, is_retryable};
+use crate::config::TelegramChat;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// The Bot API rejects longer texts outright.
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+#[derive(Serialize)]
+struct TelegramPayload {
+    chat_id: String,
+    text: String,
+    parse_mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_thread_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TelegramErrorBody {
+    parameters: Option<TelegramErrorParameters>,
+}
+
+#[derive(Deserialize)]
+struct TelegramErrorParameters {
+    retry_after: Option<u64>,
+}
+
+/// Why a single delivery attempt failed, and whether it's worth another try.
+struct SendFailure {
+    reason: String,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+pub struct TelegramNotifier {
+    token: String,
+    chat: TelegramChat,
+    max_retries: u32,
+    client: Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(client: Client, token: String, chat: TelegramChat, max_retries: u32) -> Self {
+        Self {
+            token,
+            chat,
+            max_retries,
+            client,
+        }
+    }
+
+    async fn send(&self, message: &str) -> Result<(), SendFailure> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+
+        let payload = TelegramPayload {
+            chat_id: self.chat.id.clone(),
+            text: message.to_string(),
+            parse_mode: "HTML".to_string(),
+            message_thread_id: self.chat.thread_id,
+        };
+
+        let res = self
+            .client
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SendFailure {
+                reason: format!("HTTP Request Error: {}", e),
+                retryable: true,
+                retry_after: None,
+            })?;
+
+        let status = res.status();
+        if status.is_success() {
+            return Ok(());
+        }
+
+        let err_body = res.text().await.unwrap_or_default();
+        let retry_after = serde_json::from_str::<TelegramErrorBody>(&err_body)
+            .ok()
+            .and_then(|body| body.parameters)
+            .and_then(|parameters| parameters.retry_after)
+            .map(Duration::from_secs);
+
+        Err(SendFailure {
+            reason: format!("Telegram API Error: {}", err_body),
+            retryable: is_retryable(status),
+            retry_after,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn queue_key(&self) -> String {
+        format!("telegram:{}", self.chat.id)
+    }
+
+    fn max_message_length(&self) -> Option<usize> {
+        Some(MAX_MESSAGE_LENGTH)
+    }
+
+    /// Merged messages (`batch`, `digest`) span several kinds, so they reach
+    /// every chat regardless of its subscriptions.
+    fn accepts(&self, notification: &Notification) -> bool {
+        match &self.chat.events {
+            Some(events) => {
+                matches!(notification.event_type.as_str(), "batch" | "digest")
+                    || events.contains(&notification.event_type)
+            }
+            None => true,
+        }
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let message = &notification.text;
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            let failure = match self.send(message).await {
+                Ok(()) => return Ok(()),
+                Err(failure) => failure,
+            };
+            warn!(
+                chat_id = %self.chat.id,
+                reason = %failure.reason,
+                attempt,
+                "Telegram delivery failed"
+            );
+
+            if !failure.retryable || attempt > self.max_retries {
+                return Err(NotifyError {
+                    reason: format!("{} (gave up after {attempt} attempts)", failure.reason),
+                    retryable: failure.retryable,
+                });
+            }
+
+            sleep(failure.retry_after.unwrap_or(backoff)).await;
+            backoff *= 2;
+        }
+    }
+}
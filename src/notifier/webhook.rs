@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use reqwest::{
+    Client,
+    header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue},
+};
+
+use super::{Notification, Notifier, NotifyError};
+use crate::templates::render_verbatim;
+
+pub const DEFAULT_WEBHOOK_TEMPLATE: &str =
+    r#"{"message": "{message}", "player": "{player}", "event_type": "{event_type}"}"#;
+
+/// POSTs a user-defined JSON body to an arbitrary URL.
+pub struct WebhookNotifier {
+    url: String,
+    template: String,
+    headers: HeaderMap,
+    client: Client,
+}
+
+impl WebhookNotifier {
+    /// Header names and values are expected to be validated already, see
+    /// [`parse_header`].
+    pub fn new(
+        client: Client,
+        url: String,
+        template: String,
+        headers: Vec<(String, String)>,
+    ) -> Self {
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| parse_header(name, value).ok())
+            .collect();
+        Self {
+            url,
+            template,
+            headers,
+            client,
+        }
+    }
+}
+
+pub fn parse_header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("`{name}` is not a valid header name"))?;
+    let value = HeaderValue::from_str(value)
+        .map_err(|_| format!("the value of header `{name}` is not valid"))?;
+    Ok((name, value))
+}
+
+/// Substitutes the placeholders in one pass, escaping the values so they can
+/// sit inside JSON strings in the template.
+pub fn render_body(template: &str, message: &str, player: &str, event_type: &str) -> String {
+    render_verbatim(
+        template,
+        &[
+            ("message", &json_escape(message)),
+            ("player", &json_escape(player)),
+            ("event_type", &json_escape(event_type)),
+        ],
+    )
+}
+
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    /// Generic endpoints can't be assumed to render HTML.
+    fn format(&self, notification: &Notification) -> String {
+        notification.plain_text("")
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let body = render_body(
+            &self.template,
+            &notification.text,
+            notification.player.as_deref().unwrap_or_default(),
+            &notification.event_type,
+        );
+
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(self.headers.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await;
+        let res =
+            response.map_err(|e| NotifyError::transient(format!("HTTP Request Error: {e}")))?;
+        let status = res.status();
+        if !status.is_success() {
+            let err_body = res.text().await.unwrap_or_default();
+            return Err(NotifyError::from_status(
+                status,
+                format!("Webhook Error: {err_body}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_in_values_are_not_expanded() {
+        let body = render_body(
+            DEFAULT_WEBHOOK_TEMPLATE,
+            "\"{player}\" says {event_type}",
+            "Alice",
+            "chat",
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["message"], "\"{player}\" says {event_type}");
+        assert_eq!(body["player"], "Alice");
+    }
+
+    #[test]
+    fn nested_objects_keep_their_braces() {
+        let body = render_body(r#"{"data": {"text": "{message}"}}"#, "hi", "", "");
+        assert_eq!(body, r#"{"data": {"text": "hi"}}"#);
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// A roster-changing line from the Factorio log.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LogEvent<'a> {
+    Join(&'a str),
+    Leave(&'a str),
+    SessionStarted,
+}
+
+/// Substring of the banner Factorio logs when a new server session starts.
+pub const DEFAULT_SESSION_MARKER: &str = "Server Session Started";
+
+/// Recognises `JOIN | tick | name` and `LEAVE | tick | name` lines as well as
+/// lines containing `session_marker`, the server's session start banner. Only
+/// the first two `|` are delimiters, so names may contain the character themselves.
+pub fn parse_log_line<'a>(line: &'a str, session_marker: &str) -> Option<LogEvent<'a>> {
+    if line.contains(session_marker) {
+        return Some(LogEvent::SessionStarted);
+    }
+
+    let mut parts = line.splitn(3, '|').map(|s| s.trim());
+    let (Some(action), Some(_tick), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    if name.is_empty() {
+        return None;
+    }
+
+    match action {
+        "JOIN" => Some(LogEvent::Join(name)),
+        "LEAVE" => Some(LogEvent::Leave(name)),
+        _ => None,
+    }
+}
+
+/// Extracts the author and text from a `[CHAT] Author: text` line.
+/// Messages sent from the server console are ignored.
+pub fn parse_chat_line(line: &str) -> Option<(&str, &str)> {
+    let (_, rest) = line.split_once("[CHAT] ")?;
+    let (author, text) = rest.split_once(": ")?;
+    let author = author.trim();
+
+    if author.is_empty() || author == "<server>" {
+        return None;
+    }
+
+    Some((author, text))
+}
+
+/// How a server writes joins, leaves and chat to its log.
+pub trait LogParser: Send + Sync {
+    /// Recognises join and leave lines, and lines containing `session_marker`.
+    fn parse_log_line<'a>(&self, line: &'a str, session_marker: &str) -> Option<LogEvent<'a>>;
+
+    /// Returns the author and text of a chat message.
+    fn parse_chat<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        parse_chat_line(line)
+    }
+}
+
+/// `JOIN | tick | name` lines as written by the usual logging scenario, see
+/// [`parse_log_line`].
+pub struct VanillaParser;
+
+impl LogParser for VanillaParser {
+    fn parse_log_line<'a>(&self, line: &'a str, session_marker: &str) -> Option<LogEvent<'a>> {
+        parse_log_line(line, session_marker)
+    }
+}
+
+/// What Factorio itself writes with `--console-log`: `[JOIN] name joined the
+/// game` and `[LEAVE] name left the game`, for servers without a logging mod.
+pub struct ConsoleLogParser;
+
+impl LogParser for ConsoleLogParser {
+    fn parse_log_line<'a>(&self, line: &'a str, session_marker: &str) -> Option<LogEvent<'a>> {
+        if line.contains(session_marker) {
+            return Some(LogEvent::SessionStarted);
+        }
+
+        let (event, name): (fn(&'a str) -> LogEvent<'a>, _) =
+            if let Some((_, rest)) = line.split_once("[JOIN] ") {
+                (LogEvent::Join, rest.strip_suffix(" joined the game")?)
+            } else if let Some((_, rest)) = line.split_once("[LEAVE] ") {
+                (LogEvent::Leave, rest.strip_suffix(" left the game")?)
+            } else {
+                return None;
+            };
+        let name = name.trim();
+        (!name.is_empty()).then(|| event(name))
+    }
+}
+
+/// Names accepted by `LOG_FORMAT_PRESET`.
+pub const PRESETS: &[&str] = &["vanilla", "console"];
+
+/// The parser for a `LOG_FORMAT_PRESET` name.
+pub fn preset(name: &str) -> Option<Box<dyn LogParser>> {
+    match name {
+        "vanilla" => Some(Box::new(VanillaParser)),
+        "console" => Some(Box::new(ConsoleLogParser)),
+        _ => None,
+    }
+}
+
+/// Splits the `2024-01-31 12:00:00` timestamp off the front of a log line,
+/// skipping the uptime column `factorio-current.log` puts before it. Lines
+/// without a timestamp are returned unchanged. Times are read as local to
+/// `timezone` and converted to UTC.
+pub fn split_timestamp(line: &str, timezone: Tz) -> (Option<DateTime<Utc>>, &str) {
+    let trimmed = line.trim_start();
+    let dated = match trimmed.split_once(' ') {
+        Some((uptime, rest)) if uptime.parse::<f64>().is_ok() => rest.trim_start(),
+        _ => trimmed,
+    };
+
+    match NaiveDateTime::parse_and_remainder(dated, "%Y-%m-%d %H:%M:%S%.f") {
+        Ok((timestamp, rest)) => {
+            let rest = rest.strip_prefix(';').unwrap_or(rest).trim_start();
+            // Times skipped by a DST change can't be local, so take them as UTC
+            let at = timezone
+                .from_local_datetime(&timestamp)
+                .earliest()
+                .map_or_else(|| timestamp.and_utc(), |at| at.with_timezone(&Utc));
+            (Some(at), rest)
+        }
+        Err(_) => (None, line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_join() {
+        assert_eq!(
+            parse_log_line("JOIN|1234|Alice", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Join("Alice"))
+        );
+    }
+
+    #[test]
+    fn parses_leave() {
+        assert_eq!(
+            parse_log_line("LEAVE|1234|Alice", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Leave("Alice"))
+        );
+    }
+
+    #[test]
+    fn parses_session_start() {
+        assert_eq!(
+            parse_log_line(
+                "2024-01-01 00:00:00 [INFO] Server Session Started",
+                DEFAULT_SESSION_MARKER
+            ),
+            Some(LogEvent::SessionStarted)
+        );
+    }
+
+    #[test]
+    fn console_preset_parses_factorio_console_log() {
+        let parser = preset("console").unwrap();
+        assert_eq!(
+            parser.parse_log_line(
+                "2024-01-31 12:00:00 [JOIN] Alice joined the game",
+                DEFAULT_SESSION_MARKER
+            ),
+            Some(LogEvent::Join("Alice"))
+        );
+        assert_eq!(
+            parser.parse_log_line("[LEAVE] Alice left the game", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Leave("Alice"))
+        );
+        assert_eq!(
+            parser.parse_log_line("JOIN|1234|Alice", DEFAULT_SESSION_MARKER),
+            None
+        );
+        assert_eq!(parser.parse_chat("[CHAT] Alice: hi"), Some(("Alice", "hi")));
+    }
+
+    #[test]
+    fn uses_custom_session_marker() {
+        assert_eq!(
+            parse_log_line("[wrapper] world loaded", "world loaded"),
+            Some(LogEvent::SessionStarted)
+        );
+        assert_eq!(
+            parse_log_line("Server Session Started", "world loaded"),
+            None
+        );
+    }
+
+    #[test]
+    fn trims_whitespace_around_fields() {
+        assert_eq!(
+            parse_log_line("  JOIN | 1234 |  Alice  ", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Join("Alice"))
+        );
+        assert_eq!(
+            parse_log_line("LEAVE\t|\t1234\t|\tAlice", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Leave("Alice"))
+        );
+    }
+
+    #[test]
+    fn ignores_malformed_lines() {
+        assert_eq!(parse_log_line("", DEFAULT_SESSION_MARKER), None);
+        assert_eq!(parse_log_line("JOIN", DEFAULT_SESSION_MARKER), None);
+        assert_eq!(parse_log_line("JOIN|1234", DEFAULT_SESSION_MARKER), None);
+        assert_eq!(parse_log_line("JOIN|1234|", DEFAULT_SESSION_MARKER), None);
+        assert_eq!(
+            parse_log_line("JOIN|1234|   ", DEFAULT_SESSION_MARKER),
+            None
+        );
+        assert_eq!(
+            parse_log_line("KICK|1234|Alice", DEFAULT_SESSION_MARKER),
+            None
+        );
+        assert_eq!(
+            parse_log_line("join|1234|Alice", DEFAULT_SESSION_MARKER),
+            None
+        );
+        assert_eq!(
+            parse_log_line("2024-01-01 [CHAT] Alice: hi", DEFAULT_SESSION_MARKER),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_delimiters_inside_names() {
+        assert_eq!(
+            parse_log_line("JOIN|1234|foo|bar", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Join("foo|bar"))
+        );
+        assert_eq!(
+            parse_log_line("LEAVE | 1234 | foo|bar", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Leave("foo|bar"))
+        );
+        assert_eq!(
+            parse_log_line("JOIN|1234||", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Join("|"))
+        );
+        assert_eq!(
+            parse_log_line("JOIN|1234| a | b |", DEFAULT_SESSION_MARKER),
+            Some(LogEvent::Join("a | b |"))
+        );
+    }
+
+    fn utc(timestamp: &str) -> Option<DateTime<Utc>> {
+        Some(
+            NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+                .unwrap()
+                .and_utc(),
+        )
+    }
+
+    #[test]
+    fn splits_console_log_timestamp() {
+        assert_eq!(
+            split_timestamp("2024-01-31 12:34:56 JOIN|1234|Alice", Tz::UTC),
+            (utc("2024-01-31 12:34:56"), "JOIN|1234|Alice")
+        );
+    }
+
+    #[test]
+    fn splits_timestamp_after_uptime_column() {
+        assert_eq!(
+            split_timestamp("   0.012 2024-01-31 12:34:56; Factorio 2.0.28", Tz::UTC),
+            (utc("2024-01-31 12:34:56"), "Factorio 2.0.28")
+        );
+    }
+
+    #[test]
+    fn keeps_fractional_seconds() {
+        assert_eq!(
+            split_timestamp("2024-01-31 12:34:56.250 [CHAT] Alice: hi", Tz::UTC),
+            (utc("2024-01-31 12:34:56.250"), "[CHAT] Alice: hi")
+        );
+    }
+
+    #[test]
+    fn converts_log_timezone_to_utc() {
+        assert_eq!(
+            split_timestamp("2024-07-01 12:00:00 JOIN|1234|Alice", Tz::Europe__Berlin),
+            (utc("2024-07-01 10:00:00"), "JOIN|1234|Alice")
+        );
+    }
+
+    #[test]
+    fn leaves_undated_lines_alone() {
+        assert_eq!(
+            split_timestamp("JOIN|1234|Alice", Tz::UTC),
+            (None, "JOIN|1234|Alice")
+        );
+        assert_eq!(
+            split_timestamp("  12.345 Info ServerMultiplayerManager.cpp", Tz::UTC),
+            (None, "  12.345 Info ServerMultiplayerManager.cpp")
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    config::Settings,
+    parser::{DEFAULT_SESSION_MARKER, LogEvent, LogParser, PRESETS, preset, split_timestamp},
+};
+
+/// Matches death lines written alongside JOIN/LEAVE, e.g. `DEATH | 1234 | Player | biter`.
+/// The cause column is optional.
+const DEFAULT_DEATH_PATTERN: &str =
+    r"^\s*DEATH\s*\|\s*\d+\s*\|\s*(?P<name>[^|]+?)\s*(?:\|\s*(?P<cause>.*?)\s*)?$";
+
+/// Matches Factorio's `[KICK] Player was kicked by Admin. Reason: griefing.`
+const DEFAULT_KICK_PATTERN: &str =
+    r"\[KICK\]\s+(?P<name>.+?) was kicked(?: by [^.]+)?\.(?:\s*Reason:\s*(?P<reason>.*?)\.?)?\s*$";
+
+/// Matches Factorio's `[BAN] Player was banned by Admin. Reason: griefing.`, which
+/// adds `(not on map)` for players banned while offline.
+const DEFAULT_BAN_PATTERN: &str = r"\[BAN\]\s+(?P<name>.+?)(?: \(not on map\))? was banned(?: by [^.]+)?\.(?:\s*Reason:\s*(?P<reason>.*?)\.?)?\s*$";
+
+/// Matches the refusal Factorio logs when a banned player tries to connect, e.g.
+/// `Refusing connection for address (IP ADDR:({1.2.3.4:34197})), username (Player). UserBanned`.
+const DEFAULT_BAN_ATTEMPT_PATTERN: &str =
+    r"Refusing connection for address .*?, username \((?P<name>.+?)\)\.\s*UserBanned";
+
+/// Matches research lines written by scenario scripts alongside JOIN/LEAVE, e.g.
+/// `RESEARCH | 1234 | Logistics 3`. Vanilla Factorio doesn't log research.
+const DEFAULT_RESEARCH_PATTERN: &str = r"^\s*RESEARCH\s*\|\s*\d+\s*\|\s*(?P<technology>.+?)\s*$";
+
+/// Matches achievement lines written by scenario scripts alongside JOIN/LEAVE, e.g.
+/// `ACHIEVEMENT | 1234 | Player | Smoke me a kipper`.
+const DEFAULT_ACHIEVEMENT_PATTERN: &str =
+    r"^\s*ACHIEVEMENT\s*\|\s*\d+\s*\|\s*(?P<name>[^|]+?)\s*\|\s*(?P<achievement>.+?)\s*$";
+
+/// Matches the version banner Factorio writes first thing on start-up, e.g.
+/// `Factorio 2.0.28 (build 80378, linux64, headless)`.
+const DEFAULT_STARTING_PATTERN: &str = r"^Factorio \d+\.\d+\.\d+ \(build";
+
+/// Matches the state change Factorio logs once the map has loaded.
+const DEFAULT_MAP_LOADED_PATTERN: &str = r"changing state from\(CreatingGame\) to\(InGame\)";
+
+/// What a log line, stripped of its timestamp, means to the dashboard.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParsedLine {
+    ServerStarting,
+    MapLoaded,
+    SessionStarted,
+    Chat {
+        author: String,
+        text: String,
+    },
+    Death {
+        player: String,
+        cause: Option<String>,
+    },
+    Kick {
+        player: String,
+        reason: Option<String>,
+    },
+    Ban {
+        player: String,
+        reason: Option<String>,
+    },
+    BanAttempt {
+        player: String,
+    },
+    Research {
+        technology: String,
+    },
+    Achievement {
+        player: String,
+        achievement: String,
+    },
+    Join {
+        player: String,
+    },
+    Leave {
+        player: String,
+    },
+    Unrecognized,
+}
+
+/// User-configurable regexes for log lines whose format differs between mods,
+/// the session start marker and the timezone the log's timestamps are written in. Each player pattern
+/// must define a `name` capture group; extra groups are optional. Joins, leaves
+/// and chat are read by the parser picked with `LOG_FORMAT_PRESET`.
+pub struct LogPatterns {
+    parser: Box<dyn LogParser>,
+    /// The `LOG_FORMAT_PRESET` the parser was picked with.
+    preset: String,
+    death: Regex,
+    kick: Regex,
+    ban: Regex,
+    ban_attempt: Regex,
+    research: Regex,
+    achievement: Regex,
+    starting: Regex,
+    map_loaded: Regex,
+    /// Substring marking the start of a new server session.
+    session_marker: String,
+    timezone: Tz,
+}
+
+impl LogPatterns {
+    /// Returns every invalid pattern at once rather than just the first.
+    pub fn load(settings: &Settings) -> Result<Self, Vec<String>> {
+        let death =
+            pattern_from_settings(settings, "DEATH_PATTERN", DEFAULT_DEATH_PATTERN, &["name"]);
+        let kick = pattern_from_settings(settings, "KICK_PATTERN", DEFAULT_KICK_PATTERN, &["name"]);
+        let ban = pattern_from_settings(settings, "BAN_PATTERN", DEFAULT_BAN_PATTERN, &["name"]);
+        let ban_attempt = pattern_from_settings(
+            settings,
+            "BAN_ATTEMPT_PATTERN",
+            DEFAULT_BAN_ATTEMPT_PATTERN,
+            &["name"],
+        );
+        let research = pattern_from_settings(
+            settings,
+            "RESEARCH_PATTERN",
+            DEFAULT_RESEARCH_PATTERN,
+            &["technology"],
+        );
+        let achievement = pattern_from_settings(
+            settings,
+            "ACHIEVEMENT_PATTERN",
+            DEFAULT_ACHIEVEMENT_PATTERN,
+            &["name", "achievement"],
+        );
+        let starting =
+            pattern_from_settings(settings, "STARTING_PATTERN", DEFAULT_STARTING_PATTERN, &[]);
+        let map_loaded = pattern_from_settings(
+            settings,
+            "MAP_LOADED_PATTERN",
+            DEFAULT_MAP_LOADED_PATTERN,
+            &[],
+        );
+        let timezone = timezone_from_settings(settings, "LOG_TIMEZONE");
+        let preset = settings
+            .get("LOG_FORMAT_PRESET")
+            .unwrap_or_else(|| "vanilla".to_string());
+        let parser = parser_from_settings(&preset, "LOG_FORMAT_PRESET");
+        match (
+            parser,
+            death,
+            kick,
+            ban,
+            ban_attempt,
+            research,
+            achievement,
+            starting,
+            map_loaded,
+            timezone,
+        ) {
+            (
+                Ok(parser),
+                Ok(death),
+                Ok(kick),
+                Ok(ban),
+                Ok(ban_attempt),
+                Ok(research),
+                Ok(achievement),
+                Ok(starting),
+                Ok(map_loaded),
+                Ok(timezone),
+            ) => Ok(Self {
+                parser,
+                preset,
+                death,
+                kick,
+                ban,
+                ban_attempt,
+                research,
+                achievement,
+                starting,
+                map_loaded,
+                session_marker: settings
+                    .get("SESSION_RESET_MARKER")
+                    .unwrap_or_else(|| DEFAULT_SESSION_MARKER.to_string()),
+                timezone,
+            }),
+            (
+                parser,
+                death,
+                kick,
+                ban,
+                ban_attempt,
+                research,
+                achievement,
+                starting,
+                map_loaded,
+                timezone,
+            ) => Err([
+                parser.err(),
+                death.err(),
+                kick.err(),
+                ban.err(),
+                ban_attempt.err(),
+                research.err(),
+                achievement.err(),
+                starting.err(),
+                map_loaded.err(),
+                timezone.err(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect()),
+        }
+    }
+
+    /// Returns the name of the technology that finished researching.
+    pub fn parse_research(&self, line: &str) -> Option<String> {
+        let captures = self.research.captures(line)?;
+        let technology = captures.name("technology")?.as_str().trim();
+        (!technology.is_empty()).then(|| technology.to_string())
+    }
+
+    /// Returns the player and the achievement they unlocked.
+    pub fn parse_achievement(&self, line: &str) -> Option<(String, String)> {
+        match parse_with(&self.achievement, line, "achievement")? {
+            (name, Some(achievement)) => Some((name, achievement)),
+            (_, None) => None,
+        }
+    }
+
+    /// Classifies a line, checking the patterns in the order the watcher
+    /// does: chat before anything else that splits on `|`, joins and leaves last.
+    pub fn classify(&self, line: &str) -> ParsedLine {
+        if self.is_server_starting(line) {
+            return ParsedLine::ServerStarting;
+        }
+        if self.is_map_loaded(line) {
+            return ParsedLine::MapLoaded;
+        }
+        let event = self.parse_log_line(line);
+        if event == Some(LogEvent::SessionStarted) {
+            return ParsedLine::SessionStarted;
+        }
+        if let Some((author, text)) = self.parse_chat(line) {
+            return ParsedLine::Chat {
+                author: author.to_string(),
+                text: text.to_string(),
+            };
+        }
+        if let Some((player, cause)) = self.parse_death(line) {
+            return ParsedLine::Death { player, cause };
+        }
+        if let Some((player, reason)) = self.parse_kick(line) {
+            return ParsedLine::Kick { player, reason };
+        }
+        if let Some((player, reason)) = self.parse_ban(line) {
+            return ParsedLine::Ban { player, reason };
+        }
+        if let Some(player) = self.parse_ban_attempt(line) {
+            return ParsedLine::BanAttempt { player };
+        }
+        if let Some(technology) = self.parse_research(line) {
+            return ParsedLine::Research { technology };
+        }
+        if let Some((player, achievement)) = self.parse_achievement(line) {
+            return ParsedLine::Achievement {
+                player,
+                achievement,
+            };
+        }
+        match event {
+            Some(LogEvent::Join(player)) => ParsedLine::Join {
+                player: player.to_string(),
+            },
+            Some(LogEvent::Leave(player)) => ParsedLine::Leave {
+                player: player.to_string(),
+            },
+            _ => ParsedLine::Unrecognized,
+        }
+    }
+
+    /// Name of the `LOG_FORMAT_PRESET` in use.
+    pub fn preset(&self) -> &str {
+        &self.preset
+    }
+
+    /// Whether the line shows the server process starting up.
+    pub fn is_server_starting(&self, line: &str) -> bool {
+        self.starting.is_match(line)
+    }
+
+    /// Whether the line shows the map has finished loading.
+    pub fn is_map_loaded(&self, line: &str) -> bool {
+        self.map_loaded.is_match(line)
+    }
+
+    pub fn session_marker(&self) -> &str {
+        &self.session_marker
+    }
+
+    /// Parses join, leave and session start lines.
+    pub fn parse_log_line<'a>(&self, line: &'a str) -> Option<LogEvent<'a>> {
+        self.parser.parse_log_line(line, &self.session_marker)
+    }
+
+    /// Returns the author and text of a chat message.
+    pub fn parse_chat<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+        self.parser.parse_chat(line)
+    }
+
+    /// Splits the timestamp off a line, converting it from the log's timezone.
+    pub fn split_timestamp<'a>(&self, line: &'a str) -> (Option<DateTime<Utc>>, &'a str) {
+        split_timestamp(line, self.timezone)
+    }
+
+    /// Returns the player name and, when the log names one, the cause of death.
+    pub fn parse_death(&self, line: &str) -> Option<(String, Option<String>)> {
+        parse_with(&self.death, line, "cause")
+    }
+
+    /// Returns the kicked player and the reason given, if any.
+    pub fn parse_kick(&self, line: &str) -> Option<(String, Option<String>)> {
+        parse_with(&self.kick, line, "reason")
+    }
+
+    /// Returns the banned player and the reason given, if any.
+    pub fn parse_ban(&self, line: &str) -> Option<(String, Option<String>)> {
+        parse_with(&self.ban, line, "reason")
+    }
+
+    /// Returns the banned player who was refused a connection.
+    pub fn parse_ban_attempt(&self, line: &str) -> Option<String> {
+        let captures = self.ban_attempt.captures(line)?;
+        let name = captures.name("name")?.as_str().trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+}
+
+/// Captures the `name` group along with an optional, non-empty `detail` group.
+fn parse_with(regex: &Regex, line: &str, detail: &str) -> Option<(String, Option<String>)> {
+    let captures = regex.captures(line)?;
+    let name = captures.name("name")?.as_str().trim().to_string();
+    let detail = captures
+        .name(detail)
+        .map(|detail| detail.as_str().trim().to_string())
+        .filter(|detail| !detail.is_empty());
+
+    Some((name, detail))
+}
+
+/// Reads an IANA timezone name such as `Europe/Berlin`, defaulting to UTC.
+pub fn timezone_from_settings(settings: &Settings, key: &str) -> Result<Tz, String> {
+    match settings.get(key) {
+        Some(name) => name
+            .parse()
+            .map_err(|_| format!("{key} must be an IANA timezone name like Europe/Berlin")),
+        None => Ok(Tz::UTC),
+    }
+}
+
+fn parser_from_settings(name: &str, key: &str) -> Result<Box<dyn LogParser>, String> {
+    preset(name).ok_or_else(|| format!("{key} must be one of {}", PRESETS.join(", ")))
+}
+
+fn pattern_from_settings(
+    settings: &Settings,
+    key: &str,
+    default: &str,
+    groups: &[&str],
+) -> Result<Regex, String> {
+    let pattern = settings.get(key).unwrap_or_else(|| default.to_string());
+    let regex = Regex::new(&pattern).map_err(|e| format!("{key} is not a valid regex: {e}"))?;
+    for &group in groups {
+        if !regex.capture_names().any(|name| name == Some(group)) {
+            return Err(format!(
+                "{key} must contain a (?P<{group}>...) capture group"
+            ));
+        }
+    }
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns() -> LogPatterns {
+        LogPatterns::load(&Settings::default()).expect("default patterns are valid")
+    }
+
+    #[test]
+    fn parses_default_achievement_line() {
+        assert_eq!(
+            patterns().parse_achievement("ACHIEVEMENT | 1234 | Alice | Smoke me a kipper"),
+            Some(("Alice".to_string(), "Smoke me a kipper".to_string()))
+        );
+    }
+
+    #[test]
+    fn achievement_needs_a_name() {
+        assert_eq!(
+            patterns().parse_achievement("ACHIEVEMENT | 1234 | Alice | "),
+            None
+        );
+    }
+
+    #[test]
+    fn join_lines_are_not_achievements() {
+        assert_eq!(patterns().parse_achievement("JOIN | 1234 | Alice"), None);
+    }
+
+    #[test]
+    fn classifies_lines_in_watcher_order() {
+        let patterns = patterns();
+        assert_eq!(
+            patterns.classify("[CHAT] Bob: JOIN | 1 | Alice"),
+            ParsedLine::Chat {
+                author: "Bob".to_string(),
+                text: "JOIN | 1 | Alice".to_string(),
+            }
+        );
+        assert_eq!(
+            patterns.classify("JOIN | 1 | Alice"),
+            ParsedLine::Join {
+                player: "Alice".to_string(),
+            }
+        );
+        assert_eq!(patterns.classify("garbage"), ParsedLine::Unrecognized);
+    }
+
+    #[test]
+    fn parses_refused_connection_of_a_banned_player() {
+        assert_eq!(
+            patterns().classify(
+                "Info ServerMultiplayerManager.cpp:1024: Refusing connection for address (IP ADDR:({1.2.3.4:34197})), username (Griefer). UserBanned"
+            ),
+            ParsedLine::BanAttempt {
+                player: "Griefer".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn detects_start_up_and_map_load() {
+        let patterns = patterns();
+        let (_, banner) = patterns.split_timestamp(
+            "   0.000 2024-01-31 12:00:00; Factorio 2.0.28 (build 80378, linux64, headless)",
+        );
+        assert!(patterns.is_server_starting(banner));
+        assert!(patterns.is_map_loaded(
+            "   4.210 Info ServerMultiplayerManager.cpp:759: updateTick(4294967295) changing state from(CreatingGame) to(InGame)"
+        ));
+        assert!(!patterns.is_server_starting("[CHAT] Alice: Factorio 2.0.28 (build 1)"));
+    }
+}
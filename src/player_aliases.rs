@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use crate::GameEvent;
+
+/// Friendly display names for players, used only when rendering notifications.
+/// Names are matched case-insensitively after trimming.
+pub struct PlayerAliases {
+    aliases: HashMap<String, String>,
+}
+
+impl PlayerAliases {
+    pub fn new(aliases: &[(String, String)]) -> Self {
+        Self {
+            aliases: aliases
+                .iter()
+                .map(|(name, alias)| (normalize(name), alias.clone()))
+                .collect(),
+        }
+    }
+
+    /// Replaces the event's player with their alias, if they have one.
+    pub fn apply(&self, event: &mut GameEvent) {
+        if let Some(name) = event.player_mut()
+            && let Some(alias) = self.aliases.get(&normalize(name))
+        {
+            name.clone_from(alias);
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
@@ -0,0 +1,36 @@
+use std::collections::HashSet;
+
+use crate::GameEvent;
+
+/// Decides which players' events are worth a notification. Names are matched
+/// case-insensitively after trimming.
+pub struct PlayerFilter {
+    ignore: HashSet<String>,
+    only: Option<HashSet<String>>,
+}
+
+impl PlayerFilter {
+    /// An empty `only` list means every player not ignored is allowed.
+    pub fn new(ignore: &[String], only: &[String]) -> Self {
+        let only: HashSet<String> = only.iter().map(|name| normalize(name)).collect();
+        Self {
+            ignore: ignore.iter().map(|name| normalize(name)).collect(),
+            only: (!only.is_empty()).then_some(only),
+        }
+    }
+
+    pub fn allows(&self, player: &str) -> bool {
+        let player = normalize(player);
+        !self.ignore.contains(&player)
+            && self.only.as_ref().is_none_or(|only| only.contains(&player))
+    }
+
+    /// Events that aren't about a single player always pass.
+    pub fn allows_event(&self, event: &GameEvent) -> bool {
+        event.player().is_none_or(|player| self.allows(player))
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
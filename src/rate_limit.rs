@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use tokio::time::{Instant, sleep};
+
+/// Token bucket allowing `per_minute` notifications per minute, with bursts
+/// of up to the same amount.
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Returns `None` when `per_minute` is zero, meaning no limit.
+    pub fn per_minute(per_minute: u32) -> Option<Self> {
+        if per_minute == 0 {
+            return None;
+        }
+
+        let capacity = f64::from(per_minute);
+        Some(Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: Instant::now(),
+        })
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub async fn acquire(&mut self) {
+        while !self.try_acquire() {
+            let missing = 1.0 - self.tokens;
+            sleep(Duration::from_secs_f64(missing / self.refill_per_sec)).await;
+        }
+    }
+}
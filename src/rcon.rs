@@ -0,0 +1,66 @@
+use rcon::Connection;
+use tokio::{net::TcpStream, sync::Mutex};
+use tracing::warn;
+
+/// A lazily connected RCON session to a Factorio server. The connection is
+/// dropped after any failure and re-established on the next command.
+pub struct RconClient {
+    address: String,
+    password: String,
+    conn: Mutex<Option<Connection<TcpStream>>>,
+}
+
+impl RconClient {
+    pub fn new(address: String, password: String) -> Self {
+        Self {
+            address,
+            password,
+            conn: Mutex::new(None),
+        }
+    }
+
+    pub async fn command(&self, command: &str) -> Result<String, rcon::Error> {
+        let mut conn = self.conn.lock().await;
+
+        let session = match conn.as_mut() {
+            Some(session) => session,
+            None => conn.insert(
+                Connection::builder()
+                    .enable_factorio_quirks(true)
+                    .connect(self.address.as_str(), &self.password)
+                    .await?,
+            ),
+        };
+
+        let result = session.cmd(command).await;
+        if let Err(e) = &result {
+            warn!(address = %self.address, error = %e, "RCON command failed, reconnecting next time");
+            *conn = None;
+        }
+        result
+    }
+
+    /// Names of the players the server currently reports as online.
+    pub async fn online_players(&self) -> Result<Vec<String>, rcon::Error> {
+        let response = self.command("/players online").await?;
+        Ok(parse_online_players(&response))
+    }
+}
+
+/// Parses the output of `/players online`, which looks like:
+///
+/// ```text
+/// Online players (2):
+///   Alice (online)
+///   Bob (online)
+/// ```
+fn parse_online_players(response: &str) -> Vec<String> {
+    response
+        .lines()
+        .skip(1)
+        .map(|line| line.trim())
+        .map(|line| line.strip_suffix("(online)").unwrap_or(line).trim_end())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
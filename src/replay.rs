@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+
+use crate::{format_duration, log_source::lossy_lines, parser::LogEvent, patterns::LogPatterns};
+
+/// One server session, from its start banner to the next one or the end of the log.
+struct Session {
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    peak: usize,
+}
+
+/// What a full pass over a log file found.
+#[derive(Default)]
+struct Summary {
+    lines: usize,
+    sessions: Vec<Session>,
+    peak: usize,
+    peak_at: Option<DateTime<Utc>>,
+    playtime: HashMap<String, Duration>,
+}
+
+/// Parses the whole log at `path` without notifying anyone and prints sessions,
+/// playtime and peak concurrency. Durations need timestamped log lines.
+///
+/// `path` may also be a directory of logs, such as the current log alongside
+/// `.gz` rotated ones, which are read oldest first as one continuous log.
+pub fn run(path: &str, patterns: &LogPatterns) -> io::Result<()> {
+    let files = log_files(Path::new(path))?;
+    let readers = files
+        .iter()
+        .map(|file| open_log(file))
+        .collect::<io::Result<Vec<_>>>()?;
+    let summary = summarize(readers, patterns)?;
+    print_summary(path, files.len(), &summary);
+    Ok(())
+}
+
+/// The logs in `path` ordered by when they were last written, or just `path`
+/// itself if it is a file.
+fn log_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && (name.contains(".log") || name.ends_with(".gz")) {
+            files.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Opens a log, decompressing it on the fly if it is gzipped.
+fn open_log(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+fn summarize(readers: Vec<Box<dyn BufRead>>, patterns: &LogPatterns) -> io::Result<Summary> {
+    let mut summary = Summary::default();
+    let mut online: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+    let mut last_at = None;
+
+    for line in readers.into_iter().flat_map(lossy_lines) {
+        let line = line?;
+        summary.lines += 1;
+        let (at, content) = patterns.split_timestamp(&line);
+        last_at = at.or(last_at);
+
+        match patterns.parse_log_line(content) {
+            Some(LogEvent::SessionStarted) => {
+                close_sessions(&mut summary, &mut online, at);
+                summary.sessions.push(Session {
+                    started_at: at,
+                    ended_at: None,
+                    peak: 0,
+                });
+            }
+            Some(LogEvent::Join(name)) => {
+                online.insert(name.to_string(), at);
+                if let Some(session) = summary.sessions.last_mut() {
+                    session.peak = session.peak.max(online.len());
+                }
+                if online.len() > summary.peak {
+                    summary.peak = online.len();
+                    summary.peak_at = at;
+                }
+            }
+            Some(LogEvent::Leave(name)) => {
+                if let Some(joined_at) = online.remove(name) {
+                    add_playtime(&mut summary, name, joined_at, at);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Whoever is still online played until the last line we know the time of
+    close_sessions(&mut summary, &mut online, last_at);
+    Ok(summary)
+}
+
+/// Ends the current session and every open play session at `at`.
+fn close_sessions(
+    summary: &mut Summary,
+    online: &mut HashMap<String, Option<DateTime<Utc>>>,
+    at: Option<DateTime<Utc>>,
+) {
+    if let Some(session) = summary.sessions.last_mut()
+        && session.ended_at.is_none()
+    {
+        session.ended_at = at;
+    }
+    for (name, joined_at) in online.drain() {
+        add_playtime(summary, &name, joined_at, at);
+    }
+}
+
+fn add_playtime(
+    summary: &mut Summary,
+    name: &str,
+    joined_at: Option<DateTime<Utc>>,
+    left_at: Option<DateTime<Utc>>,
+) {
+    let played = match (joined_at, left_at) {
+        (Some(joined_at), Some(left_at)) => (left_at - joined_at).to_std().unwrap_or_default(),
+        _ => Duration::ZERO,
+    };
+    *summary.playtime.entry(name.to_string()).or_default() += played;
+}
+
+fn print_summary(path: &str, files: usize, summary: &Summary) {
+    if files == 1 {
+        println!("Replayed {} ({} lines)", path, summary.lines);
+    } else {
+        println!(
+            "Replayed {} ({} files, {} lines)",
+            path, files, summary.lines
+        );
+    }
+
+    println!();
+    println!("Sessions: {}", summary.sessions.len());
+    for session in &summary.sessions {
+        let started = session
+            .started_at
+            .map_or_else(|| "unknown start".to_string(), |at| at.to_string());
+        let length = match (session.started_at, session.ended_at) {
+            (Some(start), Some(end)) => format_duration((end - start).to_std().unwrap_or_default()),
+            _ => "unknown length".to_string(),
+        };
+        println!("  {started}  {length}  peak {}", session.peak);
+    }
+
+    println!();
+    match summary.peak_at {
+        Some(at) => println!("Peak concurrency: {} at {}", summary.peak, at),
+        None => println!("Peak concurrency: {}", summary.peak),
+    }
+
+    let mut playtime: Vec<_> = summary.playtime.iter().collect();
+    playtime.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    println!();
+    println!("Playtime:");
+    for (player, played) in playtime {
+        println!("  {player}  {}", format_duration(*played));
+    }
+}
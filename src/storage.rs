@@ -0,0 +1,128 @@
+mod memory;
+mod sqlite;
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use crate::notifier::Notification;
+
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+#[derive(Clone, Copy)]
+pub enum PlayerAction {
+    Join,
+    Leave,
+}
+
+impl PlayerAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            PlayerAction::Join => "JOIN",
+            PlayerAction::Leave => "LEAVE",
+        }
+    }
+}
+
+/// Why a storage backend couldn't read or write.
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// A notification that a backend failed to deliver, waiting to be retried.
+pub struct QueuedNotification {
+    pub id: i64,
+    pub notifier: String,
+    pub notification: Notification,
+    /// How many retries have failed so far.
+    pub attempts: u32,
+}
+
+/// One player's time online, as recorded by [`Storage::player_playtime`].
+pub struct PlayerPlaytime {
+    pub total: Duration,
+    /// When the player last joined or left.
+    pub last_seen: DateTime<Utc>,
+    pub online: bool,
+}
+
+/// One stretch a player spent online, from [`Storage::for_each_session`].
+pub struct PlayerSession {
+    pub player: String,
+    pub joined_at: DateTime<Utc>,
+    /// `None` while the player is still online.
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+/// What a join is worth announcing, from [`Storage::join_milestones`].
+#[derive(Default)]
+pub struct JoinMilestones {
+    pub first_join: bool,
+    /// The playtime milestone, in hours, passed since the last one announced.
+    pub hours: Option<u64>,
+    /// How long the player had been away, if at least `returning_after`.
+    pub away: Option<Duration>,
+}
+
+/// Where player events, log offsets and undelivered notifications are kept,
+/// picked with `STORAGE_BACKEND`.
+pub trait Storage: Send + Sync {
+    fn record_event(
+        &self,
+        player: &str,
+        action: PlayerAction,
+        at: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    /// Writes everything buffered so far.
+    fn flush(&self) -> StorageResult<()>;
+
+    /// Where reading `log_path` last stopped, as the file's inode and a byte offset.
+    fn log_offset(&self, log_path: &str) -> StorageResult<Option<(u64, u64)>>;
+
+    fn save_log_offset(&self, log_path: &str, inode: u64, position: u64);
+
+    fn queue_notification(&self, notifier: &str, notification: &Notification) -> StorageResult<()>;
+
+    /// Every queued notification, oldest first.
+    fn queued_notifications(&self) -> StorageResult<Vec<QueuedNotification>>;
+
+    fn remove_queued_notification(&self, id: i64) -> StorageResult<()>;
+
+    /// Counts another failed retry of a queued notification.
+    fn record_failed_attempt(&self, id: i64) -> StorageResult<()>;
+
+    /// Total time each player has spent online, pairing every JOIN with the
+    /// LEAVE that follows it. A session that is still open counts up to now.
+    fn playtime(&self) -> StorageResult<HashMap<String, Duration>>;
+
+    /// Calls `f` with every recorded session, player by player in order of
+    /// joining, pairing JOINs with LEAVEs the way [`Storage::playtime`] does.
+    /// Stops early once `f` returns false. Sessions are read a page at a
+    /// time and the backend is only locked while a page is read, so `f` may
+    /// be slow without holding up writes.
+    fn for_each_session(&self, f: &mut dyn FnMut(PlayerSession) -> bool) -> StorageResult<()>;
+
+    /// Like [`Storage::playtime`] for a single player, or `None` if they have
+    /// never been seen.
+    fn player_playtime(&self, player: &str) -> StorageResult<Option<PlayerPlaytime>>;
+
+    /// When `player` last joined or left before `before`, if ever.
+    fn last_seen(
+        &self,
+        player: &str,
+        before: DateTime<Utc>,
+    ) -> StorageResult<Option<DateTime<Utc>>>;
+
+    /// What `player` joining at `at` is worth announcing: whether they were
+    /// never seen before, the highest milestone their playtime has passed
+    /// since the last one announced, and whether they are back after a long
+    /// absence. The first two are remembered, so each is only announced once.
+    fn join_milestones(&self, player: &str, at: DateTime<Utc>) -> StorageResult<JoinMilestones>;
+}
@@ -0,0 +1,79 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    JoinMilestones, PlayerAction, PlayerPlaytime, PlayerSession, QueuedNotification, Storage,
+    StorageResult,
+};
+use crate::notifier::Notification;
+
+/// Keeps nothing, for running without a database. Every read comes back empty,
+/// so no playtime is tracked, no join is announced as a milestone and the log
+/// is read from its end after each restart.
+pub struct MemoryStorage;
+
+impl Storage for MemoryStorage {
+    fn record_event(
+        &self,
+        _player: &str,
+        _action: PlayerAction,
+        _at: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn log_offset(&self, _log_path: &str) -> StorageResult<Option<(u64, u64)>> {
+        Ok(None)
+    }
+
+    fn save_log_offset(&self, _log_path: &str, _inode: u64, _position: u64) {}
+
+    fn queue_notification(
+        &self,
+        _notifier: &str,
+        _notification: &Notification,
+    ) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn queued_notifications(&self) -> StorageResult<Vec<QueuedNotification>> {
+        Ok(Vec::new())
+    }
+
+    fn remove_queued_notification(&self, _id: i64) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn record_failed_attempt(&self, _id: i64) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn playtime(&self) -> StorageResult<HashMap<String, Duration>> {
+        Ok(HashMap::new())
+    }
+
+    fn for_each_session(&self, _f: &mut dyn FnMut(PlayerSession) -> bool) -> StorageResult<()> {
+        Ok(())
+    }
+
+    fn player_playtime(&self, _player: &str) -> StorageResult<Option<PlayerPlaytime>> {
+        Ok(None)
+    }
+
+    fn last_seen(
+        &self,
+        _player: &str,
+        _before: DateTime<Utc>,
+    ) -> StorageResult<Option<DateTime<Utc>>> {
+        Ok(None)
+    }
+
+    fn join_milestones(&self, _player: &str, _at: DateTime<Utc>) -> StorageResult<JoinMilestones> {
+        Ok(JoinMilestones::default())
+    }
+}
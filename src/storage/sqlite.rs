@@ -0,0 +1,718 @@
+use std::{collections::HashMap, path::Path, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use super::{
+    JoinMilestones, PlayerAction, PlayerPlaytime, PlayerSession, QueuedNotification, Storage,
+    StorageResult,
+};
+use crate::notifier::Notification;
+
+/// Events read per lock of the connection by [`Storage::for_each_session`].
+const SESSION_PAGE_SIZE: usize = 1000;
+
+/// Player, action, timestamp and id of a `player_events` row.
+type EventRow = (String, String, i64, i64);
+
+/// Writes held back until the next flush.
+#[derive(Default)]
+struct PendingWrites {
+    events: Vec<(String, PlayerAction, i64)>,
+    /// Latest inode and position per log path.
+    offsets: HashMap<String, (u64, u64)>,
+}
+
+/// What `players` keeps of a player's playtime.
+#[derive(Default)]
+struct CachedPlayer {
+    milestone_hours: u64,
+    /// Closed sessions only.
+    playtime_secs: i64,
+    /// When the open session started, if there is one.
+    joined_at: Option<i64>,
+}
+
+/// Records player join/leave events in SQLite so playtime survives restarts.
+/// Events and log offsets are buffered and written in one transaction once
+/// `flush_after` events are waiting, or whenever [`Storage::flush`] is
+/// called. Reads add what is still pending to the database's contents rather
+/// than flushing it. Sessions shorter than `min_session` are kept as events
+/// but don't count toward playtime.
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+    pending: Mutex<PendingWrites>,
+    flush_after: usize,
+    min_session: Duration,
+    milestone_hours: Vec<u64>,
+    /// How long a player must have been away to be welcomed back. Zero turns
+    /// it off.
+    returning_after: Duration,
+}
+
+impl SqliteStorage {
+    pub fn open(
+        path: impl AsRef<Path>,
+        flush_after: usize,
+        min_session: Duration,
+        milestone_hours: Vec<u64>,
+        returning_after: Duration,
+    ) -> StorageResult<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS player_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                player TEXT NOT NULL,
+                action TEXT NOT NULL,
+                timestamp INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_player_events_player
+                ON player_events (player, timestamp);
+            CREATE TABLE IF NOT EXISTS log_offsets (
+                log_path TEXT PRIMARY KEY,
+                inode INTEGER NOT NULL,
+                position INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS notification_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                notifier TEXT NOT NULL,
+                text TEXT NOT NULL,
+                player TEXT,
+                event_type TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS players (
+                name TEXT PRIMARY KEY,
+                first_seen INTEGER NOT NULL,
+                milestone_hours INTEGER NOT NULL DEFAULT 0,
+                playtime_secs INTEGER NOT NULL DEFAULT 0,
+                joined_at INTEGER
+            );",
+        )?;
+        // Queues created before retries were counted lack the column
+        if !has_column(&conn, "notification_queue", "attempts")? {
+            conn.execute(
+                "ALTER TABLE notification_queue ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+        let caches_playtime = has_column(&conn, "players", "playtime_secs")?;
+        if !caches_playtime {
+            conn.execute_batch(
+                "ALTER TABLE players ADD COLUMN playtime_secs INTEGER NOT NULL DEFAULT 0;
+                 ALTER TABLE players ADD COLUMN joined_at INTEGER;",
+            )?;
+        }
+
+        // Players recorded before this table existed aren't new
+        let seeded = conn.execute(
+            "INSERT OR IGNORE INTO players (name, first_seen)
+                SELECT player, MIN(timestamp) FROM player_events GROUP BY player",
+            [],
+        )?;
+
+        let storage = Self {
+            conn: Mutex::new(conn),
+            pending: Mutex::new(PendingWrites::default()),
+            flush_after: flush_after.max(1),
+            min_session,
+            milestone_hours,
+            returning_after,
+        };
+        // Rows that didn't come in through a join have their playtime counted
+        if !caches_playtime || seeded > 0 {
+            storage.rebuild_playtime()?;
+        }
+        Ok(storage)
+    }
+
+    /// Counts every player's playtime from their events into `players`, for
+    /// databases from before it was kept there or players seeded from events.
+    fn rebuild_playtime(&self) -> StorageResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        // Closed sessions in seconds, and when the open one started
+        let mut totals: HashMap<String, (i64, Option<i64>)> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT player, action, timestamp FROM player_events ORDER BY player, timestamp, id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?;
+            for row in rows {
+                let (player, action, timestamp) = row?;
+                let (total, joined_at) = totals.entry(player).or_default();
+                match action.as_str() {
+                    "JOIN" => *joined_at = Some(timestamp),
+                    "LEAVE" => {
+                        if let Some(joined_at) = joined_at.take() {
+                            *total += self.session(joined_at, timestamp).as_secs() as i64;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for (player, (total, joined_at)) in totals {
+            tx.execute(
+                "UPDATE players SET playtime_secs = ?2, joined_at = ?3 WHERE name = ?1",
+                params![player, total, joined_at],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Up to [`SESSION_PAGE_SIZE`] events that sort after `after`, by player,
+    /// time and id. The connection is only held while the page is read, so
+    /// whoever consumes it can take as long as they like.
+    fn events_after(&self, after: &(String, i64, i64)) -> StorageResult<Vec<EventRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT player, action, timestamp, id FROM player_events
+             WHERE (player, timestamp, id) > (?1, ?2, ?3)
+             ORDER BY player, timestamp, id LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(
+            params![after.0, after.1, after.2, SESSION_PAGE_SIZE as i64],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// `cached` with the writes still pending for `player` applied, the way
+    /// [`Storage::flush`] will apply them to the database.
+    fn with_pending(
+        &self,
+        mut cached: CachedPlayer,
+        pending: &PendingWrites,
+        player: &str,
+    ) -> CachedPlayer {
+        for (_, action, timestamp) in pending.events.iter().filter(|(name, ..)| name == player) {
+            self.apply(&mut cached, *action, *timestamp);
+        }
+        cached
+    }
+
+    fn apply(&self, cached: &mut CachedPlayer, action: PlayerAction, timestamp: i64) {
+        match action {
+            PlayerAction::Join => cached.joined_at = Some(timestamp),
+            PlayerAction::Leave => {
+                if let Some(joined_at) = cached.joined_at.take() {
+                    cached.playtime_secs += self.session(joined_at, timestamp).as_secs() as i64;
+                }
+            }
+        }
+    }
+
+    /// The playtime in `cached`, counting an open session up to `now`.
+    fn total(&self, cached: &CachedPlayer, now: i64) -> Duration {
+        let open = cached
+            .joined_at
+            .map_or(Duration::ZERO, |joined_at| self.session(joined_at, now));
+        Duration::from_secs(cached.playtime_secs.max(0) as u64) + open
+    }
+
+    /// Length of a session between two timestamps, or zero if it was too short
+    /// to count.
+    fn session(&self, joined_at: i64, left_at: i64) -> Duration {
+        let length = Duration::from_secs((left_at - joined_at).max(0) as u64);
+        if length < self.min_session {
+            Duration::ZERO
+        } else {
+            length
+        }
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn record_event(
+        &self,
+        player: &str,
+        action: PlayerAction,
+        at: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        let mut pending = self.pending.lock().unwrap();
+        pending
+            .events
+            .push((player.to_string(), action, at.timestamp()));
+        if pending.events.len() < self.flush_after {
+            return Ok(());
+        }
+        drop(pending);
+        self.flush()
+    }
+
+    fn flush(&self) -> StorageResult<()> {
+        // Held until the writes are in, so a reader sees each of them either
+        // pending or in the database, never neither
+        let mut guard = self.pending.lock().unwrap();
+        let pending = std::mem::take(&mut *guard);
+        if pending.events.is_empty() && pending.offsets.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for (player, action, timestamp) in &pending.events {
+            tx.execute(
+                "INSERT INTO player_events (player, action, timestamp) VALUES (?1, ?2, ?3)",
+                params![player, action.as_str(), timestamp],
+            )?;
+            // Keep the playtime in `players` current, the way `session` counts it
+            match action {
+                PlayerAction::Join => tx.execute(
+                    "UPDATE players SET joined_at = ?2 WHERE name = ?1",
+                    params![player, timestamp],
+                )?,
+                PlayerAction::Leave => tx.execute(
+                    "UPDATE players SET joined_at = NULL, playtime_secs = playtime_secs
+                         + CASE WHEN ?2 - joined_at >= ?3 THEN ?2 - joined_at ELSE 0 END
+                     WHERE name = ?1 AND joined_at IS NOT NULL",
+                    params![player, timestamp, self.min_session.as_secs() as i64],
+                )?,
+            };
+        }
+        for (log_path, (inode, position)) in &pending.offsets {
+            tx.execute(
+                "INSERT INTO log_offsets (log_path, inode, position) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (log_path) DO UPDATE SET inode = excluded.inode, position = excluded.position",
+                params![log_path, *inode as i64, *position as i64],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    fn log_offset(&self, log_path: &str) -> StorageResult<Option<(u64, u64)>> {
+        if let Some(offset) = self.pending.lock().unwrap().offsets.get(log_path) {
+            return Ok(Some(*offset));
+        }
+        let conn = self.conn.lock().unwrap();
+        let offset = conn
+            .query_row(
+                "SELECT inode, position FROM log_offsets WHERE log_path = ?1",
+                params![log_path],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+            .optional()?;
+        Ok(offset)
+    }
+
+    fn save_log_offset(&self, log_path: &str, inode: u64, position: u64) {
+        self.pending
+            .lock()
+            .unwrap()
+            .offsets
+            .insert(log_path.to_string(), (inode, position));
+    }
+
+    fn queue_notification(&self, notifier: &str, notification: &Notification) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO notification_queue (notifier, text, player, event_type)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                notifier,
+                notification.text,
+                notification.player,
+                notification.event_type
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn queued_notifications(&self) -> StorageResult<Vec<QueuedNotification>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, notifier, text, player, event_type, attempts
+             FROM notification_queue ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(QueuedNotification {
+                id: row.get(0)?,
+                notifier: row.get(1)?,
+                notification: Notification {
+                    text: row.get(2)?,
+                    player: row.get(3)?,
+                    event_type: row.get(4)?,
+                },
+                attempts: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn remove_queued_notification(&self, id: i64) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM notification_queue WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    fn record_failed_attempt(&self, id: i64) -> StorageResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE notification_queue SET attempts = attempts + 1 WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
+
+    fn playtime(&self) -> StorageResult<HashMap<String, Duration>> {
+        let pending = self.pending.lock().unwrap();
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, playtime_secs, joined_at FROM players")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                CachedPlayer {
+                    milestone_hours: 0,
+                    playtime_secs: row.get(1)?,
+                    joined_at: row.get(2)?,
+                },
+            ))
+        })?;
+        let mut players = rows.collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        for (player, action, timestamp) in &pending.events {
+            self.apply(
+                players.entry(player.clone()).or_default(),
+                *action,
+                *timestamp,
+            );
+        }
+
+        let now = Utc::now().timestamp();
+        Ok(players
+            .into_iter()
+            .map(|(player, cached)| (player, self.total(&cached, now)))
+            .collect())
+    }
+
+    fn for_each_session(&self, f: &mut dyn FnMut(PlayerSession) -> bool) -> StorageResult<()> {
+        self.flush()?;
+        let at = |timestamp: i64| DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        let mut open: Option<(String, i64)> = None;
+        // Sorts before every row, names can't be empty
+        let mut after = (String::new(), i64::MIN, i64::MIN);
+        loop {
+            let page = self.events_after(&after)?;
+            let Some((player, _, timestamp, id)) = page.last() else {
+                break;
+            };
+            after = (player.clone(), *timestamp, *id);
+            let last_page = page.len() < SESSION_PAGE_SIZE;
+
+            for (player, action, timestamp, _) in page {
+                // Rows come grouped by player, so a session left open here never got its LEAVE
+                if let Some((open_player, joined_at)) =
+                    open.take_if(|(open_player, _)| *open_player != player)
+                    && !f(PlayerSession {
+                        player: open_player,
+                        joined_at: at(joined_at),
+                        left_at: None,
+                    })
+                {
+                    return Ok(());
+                }
+                match action.as_str() {
+                    "JOIN" => open = Some((player, timestamp)),
+                    "LEAVE" => {
+                        if let Some((player, joined_at)) = open.take()
+                            && !f(PlayerSession {
+                                player,
+                                joined_at: at(joined_at),
+                                left_at: Some(at(timestamp)),
+                            })
+                        {
+                            return Ok(());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        if let Some((player, joined_at)) = open {
+            f(PlayerSession {
+                player,
+                joined_at: at(joined_at),
+                left_at: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn player_playtime(&self, player: &str) -> StorageResult<Option<PlayerPlaytime>> {
+        let pending = self.pending.lock().unwrap();
+        let conn = self.conn.lock().unwrap();
+        let Some(last_seen) = latest_event(&conn, &pending, player, i64::MAX)? else {
+            return Ok(None);
+        };
+        let cached = cached_player(&conn, player)?.unwrap_or_default();
+        let cached = self.with_pending(cached, &pending, player);
+        Ok(Some(PlayerPlaytime {
+            total: self.total(&cached, Utc::now().timestamp()),
+            last_seen: DateTime::from_timestamp(last_seen, 0).unwrap_or_default(),
+            online: cached.joined_at.is_some(),
+        }))
+    }
+
+    fn last_seen(
+        &self,
+        player: &str,
+        before: DateTime<Utc>,
+    ) -> StorageResult<Option<DateTime<Utc>>> {
+        let pending = self.pending.lock().unwrap();
+        let conn = self.conn.lock().unwrap();
+        let last_seen = latest_event(&conn, &pending, player, before.timestamp())?;
+        Ok(last_seen.and_then(|last_seen| DateTime::from_timestamp(last_seen, 0)))
+    }
+
+    fn join_milestones(&self, player: &str, at: DateTime<Utc>) -> StorageResult<JoinMilestones> {
+        let pending = self.pending.lock().unwrap();
+        let conn = self.conn.lock().unwrap();
+        let away = if self.returning_after.is_zero() {
+            None
+        } else {
+            // The join itself has already been recorded, so look before it
+            latest_event(&conn, &pending, player, at.timestamp())?
+                .and_then(|last_seen| DateTime::from_timestamp(last_seen, 0))
+                .and_then(|last_seen| (at - last_seen).to_std().ok())
+                .filter(|&away| away >= self.returning_after)
+        };
+
+        let Some(cached) = cached_player(&conn, player)? else {
+            conn.execute(
+                "INSERT INTO players (name, first_seen, joined_at) VALUES (?1, ?2, ?2)",
+                params![player, at.timestamp()],
+            )?;
+            return Ok(JoinMilestones {
+                first_join: true,
+                hours: None,
+                away: None,
+            });
+        };
+        let cached = self.with_pending(cached, &pending, player);
+        let announced = cached.milestone_hours;
+        let played = self.total(&cached, at.timestamp());
+        let reached = self
+            .milestone_hours
+            .iter()
+            .rev()
+            .find(|&&hours| played >= Duration::from_secs(hours * 3600))
+            .copied()
+            .unwrap_or(0);
+        if reached <= announced {
+            return Ok(JoinMilestones {
+                first_join: false,
+                hours: None,
+                away,
+            });
+        }
+        conn.execute(
+            "UPDATE players SET milestone_hours = ?2 WHERE name = ?1",
+            params![player, reached],
+        )?;
+        Ok(JoinMilestones {
+            first_join: false,
+            hours: Some(reached),
+            away,
+        })
+    }
+}
+
+/// `player`'s row in `players` as last flushed, if they have one.
+fn cached_player(conn: &Connection, player: &str) -> StorageResult<Option<CachedPlayer>> {
+    let cached = conn
+        .query_row(
+            "SELECT milestone_hours, playtime_secs, joined_at FROM players WHERE name = ?1",
+            params![player],
+            |row| {
+                Ok(CachedPlayer {
+                    milestone_hours: row.get(0)?,
+                    playtime_secs: row.get(1)?,
+                    joined_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(cached)
+}
+
+/// When `player` last joined or left before `before`, whether that was
+/// flushed yet or not.
+fn latest_event(
+    conn: &Connection,
+    pending: &PendingWrites,
+    player: &str,
+    before: i64,
+) -> StorageResult<Option<i64>> {
+    let flushed: Option<i64> = conn.query_row(
+        "SELECT MAX(timestamp) FROM player_events WHERE player = ?1 AND timestamp < ?2",
+        params![player, before],
+        |row| row.get(0),
+    )?;
+    let pending = pending
+        .events
+        .iter()
+        .filter(|(name, _, timestamp)| name == player && *timestamp < before)
+        .map(|(.., timestamp)| *timestamp)
+        .max();
+    Ok(flushed.max(pending))
+}
+
+/// Whether `table` has `column`, for adding columns to older databases.
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_span_pages() {
+        let storage =
+            SqliteStorage::open(":memory:", 100, Duration::ZERO, Vec::new(), Duration::ZERO)
+                .unwrap();
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        for player in ["Alice", "Bob"] {
+            for i in 0..SESSION_PAGE_SIZE as i64 {
+                let at = start + chrono::Duration::minutes(2 * i);
+                storage
+                    .record_event(player, PlayerAction::Join, at)
+                    .unwrap();
+                storage
+                    .record_event(
+                        player,
+                        PlayerAction::Leave,
+                        at + chrono::Duration::minutes(1),
+                    )
+                    .unwrap();
+            }
+        }
+        storage
+            .record_event("Carol", PlayerAction::Join, start)
+            .unwrap();
+
+        let mut sessions = Vec::new();
+        storage
+            .for_each_session(&mut |session| {
+                sessions.push(session);
+                true
+            })
+            .unwrap();
+
+        assert_eq!(sessions.len(), 2 * SESSION_PAGE_SIZE + 1);
+        assert!(
+            sessions
+                .iter()
+                .filter(|session| session.player != "Carol")
+                .all(|session| session.left_at.is_some())
+        );
+        let last = sessions.last().unwrap();
+        assert_eq!((last.player.as_str(), last.left_at), ("Carol", None));
+    }
+
+    #[test]
+    fn counts_failed_retries() {
+        let storage =
+            SqliteStorage::open(":memory:", 100, Duration::ZERO, Vec::new(), Duration::ZERO)
+                .unwrap();
+        let notification = Notification {
+            text: "Alice joined the game".to_string(),
+            player: Some("Alice".to_string()),
+            event_type: "join".to_string(),
+        };
+        storage
+            .queue_notification("discord", &notification)
+            .unwrap();
+        let id = storage.queued_notifications().unwrap()[0].id;
+
+        storage.record_failed_attempt(id).unwrap();
+        storage.record_failed_attempt(id).unwrap();
+
+        let queued = storage.queued_notifications().unwrap();
+        assert_eq!(queued[0].attempts, 2);
+    }
+
+    #[test]
+    fn milestones_count_cached_playtime() {
+        let storage =
+            SqliteStorage::open(":memory:", 100, Duration::ZERO, vec![1], Duration::ZERO).unwrap();
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        storage
+            .record_event("Alice", PlayerAction::Join, start)
+            .unwrap();
+        assert!(storage.join_milestones("Alice", start).unwrap().first_join);
+        storage
+            .record_event(
+                "Alice",
+                PlayerAction::Leave,
+                start + chrono::Duration::minutes(90),
+            )
+            .unwrap();
+
+        let rejoined = start + chrono::Duration::hours(3);
+        storage
+            .record_event("Alice", PlayerAction::Join, rejoined)
+            .unwrap();
+        let milestones = storage.join_milestones("Alice", rejoined).unwrap();
+        assert!(!milestones.first_join);
+        assert_eq!(milestones.hours, Some(1));
+
+        // None of that needed the events written out
+        let written: i64 = storage
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM player_events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn counts_playtime_of_players_recorded_before_the_players_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dashboard.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE player_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    player TEXT NOT NULL,
+                    action TEXT NOT NULL,
+                    timestamp INTEGER NOT NULL
+                );
+                INSERT INTO player_events (player, action, timestamp) VALUES
+                    ('Alice', 'JOIN', 1000),
+                    ('Alice', 'LEAVE', 4600),
+                    ('Bob', 'JOIN', 2000);",
+            )
+            .unwrap();
+        }
+
+        let storage =
+            SqliteStorage::open(&path, 100, Duration::ZERO, Vec::new(), Duration::ZERO).unwrap();
+        let conn = storage.conn.lock().unwrap();
+        let cached = |name: &str| -> (i64, Option<i64>) {
+            conn.query_row(
+                "SELECT playtime_secs, joined_at FROM players WHERE name = ?1",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(cached("Alice"), (3600, None));
+        assert_eq!(cached("Bob"), (0, Some(2000)));
+    }
+}
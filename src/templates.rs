@@ -0,0 +1,603 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::config::Settings;
+
+const DEFAULT_JOIN: &str = "<b>{player}</b> joined the game";
+const DEFAULT_LEAVE: &str = "<b>{player}</b> left the game";
+const DEFAULT_SESSION_RESET: &str = "Server session restarted";
+const DEFAULT_SERVER_ACTIVE: &str = "Server is active, <b>{player}</b> is the first one online";
+const DEFAULT_SERVER_EMPTY: &str = "Server is empty, <b>{player}</b> was the last to leave";
+const DEFAULT_HEARTBEAT: &str =
+    "Still running for {uptime}, {count} player(s) online, peak {peak} this session";
+
+/// English defaults for the texts of the remaining events, keyed by their
+/// `MSG_*` setting. See [`MessageTemplates::message`].
+const DEFAULT_MESSAGES: &[(&str, &str)] = &[
+    ("MSG_JOIN_MANY", "{count} players joined: {players}"),
+    ("MSG_RECONNECTED", "{count} players reconnected: {players}"),
+    ("MSG_LIST", "{names} and {last}"),
+    ("MSG_PREVIOUS_SESSION", "(previous session: {duration})"),
+    ("MSG_SERVER_STARTING", "Server is starting up..."),
+    (
+        "MSG_MAP_LOADED",
+        "Map loaded, the session is about to start",
+    ),
+    ("MSG_CHAT", "<b>{player}</b>: {text}"),
+    ("MSG_DEATH", "<b>{player}</b> died"),
+    ("MSG_DEATH_CAUSE", "<b>{player}</b> was killed by {cause}"),
+    ("MSG_KICK", "<b>{player}</b> was kicked"),
+    ("MSG_KICK_REASON", "<b>{player}</b> was kicked: {reason}"),
+    ("MSG_BAN", "<b>{player}</b> was banned"),
+    ("MSG_BAN_REASON", "<b>{player}</b> was banned: {reason}"),
+    (
+        "MSG_BAN_ATTEMPT",
+        "Banned player <b>{player}</b> tried to join",
+    ),
+    ("MSG_RESEARCH", "Research complete: <b>{technology}</b>"),
+    (
+        "MSG_ACHIEVEMENT",
+        "<b>{player}</b> unlocked the achievement <b>{achievement}</b>!",
+    ),
+    (
+        "MSG_UNRESPONSIVE",
+        "Server log has been silent for {duration} while players are online, it may have crashed",
+    ),
+    ("MSG_RECOVERED", "Server log is active again"),
+    ("MSG_NEW_PLAYER", "Welcome, new player <b>{player}</b>!"),
+    (
+        "MSG_RETURNING",
+        "Welcome back, <b>{player}</b>! (last seen {days} days ago)",
+    ),
+    (
+        "MSG_MILESTONE",
+        "<b>{player}</b> has now played for {hours} hours",
+    ),
+    ("MSG_DIGEST", "In the last {duration}: {changes}"),
+    ("MSG_DIGEST_JOINED", "{players} joined"),
+    ("MSG_DIGEST_LEFT", "{players} left"),
+];
+
+/// Built-in default texts per language, keyed by the `MSG_*` setting they
+/// stand in for. A key a language lacks falls back to English.
+const BUNDLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "de",
+        &[
+            ("MSG_JOIN", "<b>{player}</b> ist dem Spiel beigetreten"),
+            ("MSG_LEAVE", "<b>{player}</b> hat das Spiel verlassen"),
+            ("MSG_SESSION_RESET", "Serversitzung neu gestartet"),
+            (
+                "MSG_SERVER_ACTIVE",
+                "Server ist aktiv, <b>{player}</b> ist als Erstes online",
+            ),
+            (
+                "MSG_SERVER_EMPTY",
+                "Server ist leer, <b>{player}</b> ist als Letztes gegangen",
+            ),
+            (
+                "MSG_HEARTBEAT",
+                "Läuft seit {uptime}, {count} Spieler online, Höchststand {peak} in dieser Sitzung",
+            ),
+            (
+                "MSG_JOIN_MANY",
+                "{count} Spieler sind beigetreten: {players}",
+            ),
+            (
+                "MSG_RECONNECTED",
+                "{count} Spieler haben sich neu verbunden: {players}",
+            ),
+            ("MSG_LIST", "{names} und {last}"),
+            ("MSG_PREVIOUS_SESSION", "(vorherige Sitzung: {duration})"),
+            ("MSG_SERVER_STARTING", "Server startet..."),
+            (
+                "MSG_MAP_LOADED",
+                "Karte geladen, die Sitzung beginnt gleich",
+            ),
+            ("MSG_DEATH", "<b>{player}</b> ist gestorben"),
+            (
+                "MSG_DEATH_CAUSE",
+                "<b>{player}</b> wurde von {cause} getötet",
+            ),
+            ("MSG_KICK", "<b>{player}</b> wurde gekickt"),
+            ("MSG_KICK_REASON", "<b>{player}</b> wurde gekickt: {reason}"),
+            ("MSG_BAN", "<b>{player}</b> wurde gebannt"),
+            ("MSG_BAN_REASON", "<b>{player}</b> wurde gebannt: {reason}"),
+            (
+                "MSG_BAN_ATTEMPT",
+                "Der gebannte Spieler <b>{player}</b> wollte beitreten",
+            ),
+            (
+                "MSG_RESEARCH",
+                "Forschung abgeschlossen: <b>{technology}</b>",
+            ),
+            (
+                "MSG_ACHIEVEMENT",
+                "<b>{player}</b> hat den Erfolg <b>{achievement}</b> freigeschaltet!",
+            ),
+            (
+                "MSG_UNRESPONSIVE",
+                "Das Serverlog ist seit {duration} still, obwohl Spieler online sind, der Server ist eventuell abgestürzt",
+            ),
+            ("MSG_RECOVERED", "Das Serverlog ist wieder aktiv"),
+            (
+                "MSG_NEW_PLAYER",
+                "Willkommen, neuer Spieler <b>{player}</b>!",
+            ),
+            (
+                "MSG_RETURNING",
+                "Willkommen zurück, <b>{player}</b>! (zuletzt vor {days} Tagen gesehen)",
+            ),
+            (
+                "MSG_MILESTONE",
+                "<b>{player}</b> hat jetzt {hours} Stunden gespielt",
+            ),
+            ("MSG_DIGEST", "In den letzten {duration}: {changes}"),
+            ("MSG_DIGEST_JOINED", "{players} beigetreten"),
+            ("MSG_DIGEST_LEFT", "{players} gegangen"),
+        ],
+    ),
+    (
+        "ru",
+        &[
+            ("MSG_JOIN", "<b>{player}</b> присоединился к игре"),
+            ("MSG_LEAVE", "<b>{player}</b> покинул игру"),
+            ("MSG_SESSION_RESET", "Сессия сервера перезапущена"),
+            (
+                "MSG_SERVER_ACTIVE",
+                "Сервер активен, <b>{player}</b> зашёл первым",
+            ),
+            (
+                "MSG_SERVER_EMPTY",
+                "Сервер пуст, <b>{player}</b> вышел последним",
+            ),
+            (
+                "MSG_HEARTBEAT",
+                "Работает уже {uptime}, игроков онлайн: {count}, максимум за сессию: {peak}",
+            ),
+            ("MSG_JOIN_MANY", "Игроков зашло: {count}, {players}"),
+            (
+                "MSG_RECONNECTED",
+                "Игроков переподключилось: {count}, {players}",
+            ),
+            ("MSG_LIST", "{names} и {last}"),
+            ("MSG_PREVIOUS_SESSION", "(предыдущая сессия: {duration})"),
+            ("MSG_SERVER_STARTING", "Сервер запускается..."),
+            ("MSG_MAP_LOADED", "Карта загружена, сессия вот-вот начнётся"),
+            ("MSG_DEATH", "<b>{player}</b> погиб"),
+            ("MSG_DEATH_CAUSE", "<b>{player}</b> убит: {cause}"),
+            ("MSG_KICK", "<b>{player}</b> исключён с сервера"),
+            (
+                "MSG_KICK_REASON",
+                "<b>{player}</b> исключён с сервера: {reason}",
+            ),
+            ("MSG_BAN", "<b>{player}</b> забанен"),
+            ("MSG_BAN_REASON", "<b>{player}</b> забанен: {reason}"),
+            (
+                "MSG_BAN_ATTEMPT",
+                "Забаненный игрок <b>{player}</b> пытался зайти",
+            ),
+            (
+                "MSG_RESEARCH",
+                "Исследование завершено: <b>{technology}</b>",
+            ),
+            (
+                "MSG_ACHIEVEMENT",
+                "<b>{player}</b> получил достижение <b>{achievement}</b>!",
+            ),
+            (
+                "MSG_UNRESPONSIVE",
+                "Лог сервера молчит уже {duration}, хотя игроки онлайн, возможно, сервер упал",
+            ),
+            ("MSG_RECOVERED", "Лог сервера снова активен"),
+            (
+                "MSG_NEW_PLAYER",
+                "Добро пожаловать, новый игрок <b>{player}</b>!",
+            ),
+            (
+                "MSG_RETURNING",
+                "С возвращением, <b>{player}</b>! (не заходил(а) {days} дн.)",
+            ),
+            ("MSG_MILESTONE", "<b>{player}</b> наиграл уже {hours} ч."),
+            ("MSG_DIGEST", "За последние {duration}: {changes}"),
+            ("MSG_DIGEST_JOINED", "зашли: {players}"),
+            ("MSG_DIGEST_LEFT", "вышли: {players}"),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("MSG_JOIN", "<b>{player}</b> a rejoint la partie"),
+            ("MSG_LEAVE", "<b>{player}</b> a quitté la partie"),
+            ("MSG_SESSION_RESET", "Session du serveur redémarrée"),
+            (
+                "MSG_SERVER_ACTIVE",
+                "Le serveur est actif, <b>{player}</b> est le premier en ligne",
+            ),
+            (
+                "MSG_SERVER_EMPTY",
+                "Le serveur est vide, <b>{player}</b> est le dernier à partir",
+            ),
+            (
+                "MSG_HEARTBEAT",
+                "En ligne depuis {uptime}, {count} joueur(s) connecté(s), pic de {peak} cette session",
+            ),
+            (
+                "MSG_JOIN_MANY",
+                "{count} joueurs ont rejoint la partie : {players}",
+            ),
+            (
+                "MSG_RECONNECTED",
+                "{count} joueurs se sont reconnectés : {players}",
+            ),
+            ("MSG_LIST", "{names} et {last}"),
+            ("MSG_PREVIOUS_SESSION", "(session précédente : {duration})"),
+            ("MSG_SERVER_STARTING", "Le serveur démarre..."),
+            ("MSG_MAP_LOADED", "Carte chargée, la session va commencer"),
+            ("MSG_DEATH", "<b>{player}</b> est mort"),
+            ("MSG_DEATH_CAUSE", "<b>{player}</b> a été tué par {cause}"),
+            ("MSG_KICK", "<b>{player}</b> a été expulsé"),
+            (
+                "MSG_KICK_REASON",
+                "<b>{player}</b> a été expulsé : {reason}",
+            ),
+            ("MSG_BAN", "<b>{player}</b> a été banni"),
+            ("MSG_BAN_REASON", "<b>{player}</b> a été banni : {reason}"),
+            (
+                "MSG_BAN_ATTEMPT",
+                "Le joueur banni <b>{player}</b> a tenté de se connecter",
+            ),
+            ("MSG_RESEARCH", "Recherche terminée : <b>{technology}</b>"),
+            (
+                "MSG_ACHIEVEMENT",
+                "<b>{player}</b> a débloqué le succès <b>{achievement}</b> !",
+            ),
+            (
+                "MSG_UNRESPONSIVE",
+                "Le journal du serveur est silencieux depuis {duration} alors que des joueurs sont en ligne, il a peut-être planté",
+            ),
+            (
+                "MSG_RECOVERED",
+                "Le journal du serveur est de nouveau actif",
+            ),
+            (
+                "MSG_NEW_PLAYER",
+                "Bienvenue, nouveau joueur <b>{player}</b> !",
+            ),
+            (
+                "MSG_RETURNING",
+                "Bon retour, <b>{player}</b> ! (vu pour la dernière fois il y a {days} jours)",
+            ),
+            (
+                "MSG_MILESTONE",
+                "<b>{player}</b> a maintenant joué {hours} heures",
+            ),
+            (
+                "MSG_DIGEST",
+                "Au cours des dernières {duration} : {changes}",
+            ),
+            ("MSG_DIGEST_JOINED", "{players} ont rejoint"),
+            ("MSG_DIGEST_LEFT", "{players} sont partis"),
+        ],
+    ),
+];
+
+/// User-overridable notification texts with `{player}`, `{server}` and `{time}`
+/// placeholders. Joins and leaves also get `{count}`, the players online
+/// afterwards, and the heartbeat gets `{count}`, `{peak}` and `{uptime}`.
+/// `{{` and `}}` produce literal braces. Texts not set are taken from the
+/// bundle for `MSG_LANGUAGE`, e.g. `de` or `de_DE.UTF-8`, falling back to the
+/// system's `LANG` when that isn't set, or English if there is none.
+/// Joins, leaves and session resets can also be prefixed with an emoji.
+/// Every other event has a text in [`DEFAULT_MESSAGES`], filled in through
+/// [`MessageTemplates::message`].
+#[derive(Clone, Serialize)]
+pub struct MessageTemplates {
+    join: String,
+    leave: String,
+    session_reset: String,
+    heartbeat: String,
+    server_active: String,
+    server_empty: String,
+    join_emoji: String,
+    leave_emoji: String,
+    session_reset_emoji: String,
+    messages: BTreeMap<&'static str, String>,
+}
+
+impl MessageTemplates {
+    pub fn load(settings: &Settings) -> Self {
+        let language = settings
+            .get("MSG_LANGUAGE")
+            .or_else(|| settings.get("LANG"))
+            .unwrap_or_default();
+        Self::with_language(settings, &language)
+    }
+
+    /// Like [`MessageTemplates::load`], with defaults in `language` rather
+    /// than the one configured.
+    pub fn with_language(settings: &Settings, language: &str) -> Self {
+        let language = language
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let text = |key: &str, english: &str| {
+            settings.get(key).unwrap_or_else(|| {
+                BUNDLES
+                    .iter()
+                    .find(|(name, _)| *name == language)
+                    .and_then(|(_, texts)| texts.iter().find(|(name, _)| *name == key))
+                    .map_or(english, |(_, text)| text)
+                    .to_string()
+            })
+        };
+        Self {
+            join: text("MSG_JOIN", DEFAULT_JOIN),
+            leave: text("MSG_LEAVE", DEFAULT_LEAVE),
+            session_reset: text("MSG_SESSION_RESET", DEFAULT_SESSION_RESET),
+            heartbeat: text("MSG_HEARTBEAT", DEFAULT_HEARTBEAT),
+            server_active: text("MSG_SERVER_ACTIVE", DEFAULT_SERVER_ACTIVE),
+            server_empty: text("MSG_SERVER_EMPTY", DEFAULT_SERVER_EMPTY),
+            join_emoji: settings.get("EMOJI_JOIN").unwrap_or_default(),
+            leave_emoji: settings.get("EMOJI_LEAVE").unwrap_or_default(),
+            session_reset_emoji: settings.get("EMOJI_RESET").unwrap_or_default(),
+            messages: DEFAULT_MESSAGES
+                .iter()
+                .map(|(key, english)| (*key, text(key, english)))
+                .collect(),
+        }
+    }
+
+    /// Prepends the emoji set for events of `kind`, if there is one.
+    pub fn with_emoji(&self, kind: &str, message: String) -> String {
+        let emoji = match kind {
+            "join" => &self.join_emoji,
+            "leave" => &self.leave_emoji,
+            "session_reset" => &self.session_reset_emoji,
+            _ => return message,
+        };
+        if emoji.is_empty() {
+            message
+        } else {
+            format!("{emoji} {message}")
+        }
+    }
+
+    pub fn join(&self, server: &str, player: &str, time: &str, count: usize) -> String {
+        render(
+            &self.join,
+            &[
+                ("server", server),
+                ("player", player),
+                ("time", time),
+                ("count", &count.to_string()),
+            ],
+        )
+    }
+
+    pub fn leave(&self, server: &str, player: &str, time: &str, count: usize) -> String {
+        render(
+            &self.leave,
+            &[
+                ("server", server),
+                ("player", player),
+                ("time", time),
+                ("count", &count.to_string()),
+            ],
+        )
+    }
+
+    pub fn server_active(&self, server: &str, player: &str, time: &str) -> String {
+        render(
+            &self.server_active,
+            &[("server", server), ("player", player), ("time", time)],
+        )
+    }
+
+    pub fn server_empty(&self, server: &str, player: &str, time: &str) -> String {
+        render(
+            &self.server_empty,
+            &[("server", server), ("player", player), ("time", time)],
+        )
+    }
+
+    pub fn session_reset(&self, server: &str, time: &str) -> String {
+        render(&self.session_reset, &[("server", server), ("time", time)])
+    }
+
+    pub fn heartbeat(
+        &self,
+        server: &str,
+        time: &str,
+        count: usize,
+        peak: usize,
+        uptime: &str,
+    ) -> String {
+        render(
+            &self.heartbeat,
+            &[
+                ("server", server),
+                ("time", time),
+                ("count", &count.to_string()),
+                ("peak", &peak.to_string()),
+                ("uptime", uptime),
+            ],
+        )
+    }
+
+    /// Renders the text set for `key`, one of the `MSG_*` names in
+    /// [`DEFAULT_MESSAGES`].
+    pub fn message(&self, key: &str, values: &[(&str, &str)]) -> String {
+        match self.messages.get(key) {
+            Some(template) => render(template, values),
+            None => key.to_string(),
+        }
+    }
+
+    /// Lists names as `A`, `A and B` or `A, B and C`, in the configured
+    /// language.
+    pub fn list(&self, names: &[String]) -> String {
+        match names {
+            [] => String::new(),
+            [only] => only.clone(),
+            [rest @ .., last] => {
+                self.message("MSG_LIST", &[("names", &rest.join(", ")), ("last", last)])
+            }
+        }
+    }
+}
+
+/// Substitutes `{name}` placeholders in a single pass, so values that happen to
+/// contain placeholder syntax are never expanded. Unknown placeholders are kept.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    substitute(template, values, true)
+}
+
+/// Like [`render`], but `{{` and `}}` are kept as they are, since JSON
+/// templates are full of braces. Values go in verbatim, so escape them first.
+pub fn render_verbatim(template: &str, values: &[(&str, &str)]) -> String {
+    substitute(template, values, false)
+}
+
+fn substitute(template: &str, values: &[(&str, &str)], brace_escapes: bool) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        if brace_escapes && (rest.starts_with("{{") || rest.starts_with("}}")) {
+            rendered.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('}') {
+            rendered.push('}');
+            rest = &rest[1..];
+            continue;
+        }
+
+        let substitution = rest.find('}').and_then(|close| {
+            let key = &rest[1..close];
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (*value, close))
+        });
+
+        match substitution {
+            Some((value, close)) => {
+                rendered.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_placeholders() {
+        assert_eq!(
+            render(
+                "{player} joined {server}, {count} online",
+                &[("player", "Alice"), ("server", "main"), ("count", "3")]
+            ),
+            "Alice joined main, 3 online"
+        );
+    }
+
+    #[test]
+    fn keeps_missing_placeholders() {
+        assert_eq!(
+            render("{player} at {time}", &[("player", "Alice")]),
+            "Alice at {time}"
+        );
+    }
+
+    #[test]
+    fn does_not_expand_placeholders_in_values() {
+        assert_eq!(
+            render("{player}", &[("player", "{count}"), ("count", "3")]),
+            "{count}"
+        );
+    }
+
+    #[test]
+    fn escapes_doubled_braces() {
+        assert_eq!(
+            render("{{player}} is {player}", &[("player", "Alice")]),
+            "{player} is Alice"
+        );
+    }
+
+    #[test]
+    fn picks_the_bundle_for_a_locale() {
+        let templates = MessageTemplates::with_language(&Settings::default(), "de_DE.UTF-8");
+        assert_eq!(
+            templates.join("main", "Alice", "12:00", 1),
+            "<b>Alice</b> ist dem Spiel beigetreten"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_english_for_unknown_languages() {
+        let templates = MessageTemplates::with_language(&Settings::default(), "C.UTF-8");
+        assert_eq!(
+            templates.leave("main", "Alice", "12:00", 0),
+            "<b>Alice</b> left the game"
+        );
+    }
+
+    #[test]
+    fn prefixes_only_kinds_with_an_emoji() {
+        let templates = MessageTemplates {
+            join_emoji: "✅".to_string(),
+            ..MessageTemplates::with_language(&Settings::default(), "en")
+        };
+        assert_eq!(
+            templates.with_emoji("join", "Alice joined".to_string()),
+            "✅ Alice joined"
+        );
+        assert_eq!(
+            templates.with_emoji("leave", "Alice left".to_string()),
+            "Alice left"
+        );
+    }
+
+    #[test]
+    fn translates_event_texts_and_lists() {
+        let templates = MessageTemplates::with_language(&Settings::default(), "de");
+        assert_eq!(
+            templates.message(
+                "MSG_DEATH_CAUSE",
+                &[("player", "Alice"), ("cause", "a biter")]
+            ),
+            "<b>Alice</b> wurde von a biter getötet"
+        );
+        assert_eq!(
+            templates.list(&["A".to_string(), "B".to_string(), "C".to_string()]),
+            "A, B und C"
+        );
+    }
+
+    #[test]
+    fn keeps_unmatched_braces() {
+        assert_eq!(
+            render("{player} :} {", &[("player", "Alice")]),
+            "Alice :} {"
+        );
+    }
+}
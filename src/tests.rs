@@ -0,0 +1,297 @@
+//! End-to-end tests driving the log watcher and notification worker together.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{sync::broadcast, time::timeout};
+
+use super::*;
+use crate::{config::Settings, log_source::MemoryLogSource, notifier::NotifyError};
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Records every message instead of delivering it.
+#[derive(Clone, Default)]
+struct MockNotifier {
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Notifier for MockNotifier {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(notification.text.clone());
+        Ok(())
+    }
+}
+
+fn app_state(log_path: &Path) -> Arc<AppState> {
+    Arc::new(new_app_state(log_path, Duration::ZERO))
+}
+
+fn new_app_state(log_path: &Path, join_grace: Duration) -> AppState {
+    let server = ServerState::new(
+        "test".to_string(),
+        log_path.to_string_lossy().into_owned(),
+        None,
+    );
+    let (tx, _) = broadcast::channel(100);
+    let (shutdown, _) = broadcast::channel(1);
+    let patterns = LogPatterns::load(&Settings::default()).expect("default patterns are valid");
+    AppState::new(
+        vec![Arc::new(server)],
+        tx,
+        shutdown,
+        None,
+        Duration::ZERO,
+        join_grace,
+        patterns,
+        100,
+    )
+}
+
+fn append(path: &Path, lines: &[&str]) {
+    let mut file = OpenOptions::new().append(true).open(path).unwrap();
+    for line in lines {
+        writeln!(file, "{line}").unwrap();
+    }
+    file.flush().unwrap();
+}
+
+/// Starts `watch_log` and waits until it is following the file.
+async fn start_watcher(state: &Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let server = Arc::clone(&state.servers[0]);
+    let handle = tokio::spawn({
+        let state = Arc::clone(state);
+        let server = Arc::clone(&server);
+        async move {
+            watch_log(state, server, FileLogSource::default())
+                .await
+                .unwrap()
+        }
+    });
+    timeout(EVENT_TIMEOUT, async {
+        while !server.watcher_alive.load(Ordering::Relaxed) {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("watcher never started");
+    handle
+}
+
+async fn next_event(rx: &mut Receiver<ServerEvent>) -> GameEvent {
+    timeout(EVENT_TIMEOUT, rx.recv())
+        .await
+        .expect("timed out waiting for an event")
+        .expect("event channel closed")
+        .event
+}
+
+#[tokio::test]
+async fn watcher_turns_log_lines_into_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("console.log");
+    std::fs::write(&log_path, "JOIN|1|Alice\n").unwrap();
+
+    let state = app_state(&log_path);
+    let mut rx = state.tx.subscribe();
+    let watcher = start_watcher(&state).await;
+
+    append(
+        &log_path,
+        &[
+            "2024-01-31 12:00:00 JOIN | 2 | Bob",
+            "garbage that matches nothing",
+            "LEAVE|3|Alice",
+            "2024-01-31 12:00:05 [CHAT] Bob: hello | world",
+            "Server Session Started",
+        ],
+    );
+
+    match next_event(&mut rx).await {
+        GameEvent::PlayerJoined(name) => assert_eq!(name, "Bob"),
+        _ => panic!("expected Bob to join"),
+    }
+    match next_event(&mut rx).await {
+        GameEvent::PlayerLeft(name) => assert_eq!(name, "Alice"),
+        _ => panic!("expected Alice to leave"),
+    }
+    match next_event(&mut rx).await {
+        GameEvent::ChatMessage { author, text } => {
+            assert_eq!(author, "Bob");
+            assert_eq!(text, "hello | world");
+        }
+        _ => panic!("expected a chat message"),
+    }
+    assert!(matches!(
+        next_event(&mut rx).await,
+        GameEvent::SessionReset { .. }
+    ));
+    assert!(state.servers[0].online_players.read().await.is_empty());
+
+    let _ = state.shutdown.send(());
+    watcher.await.unwrap();
+}
+
+#[tokio::test]
+async fn watcher_reads_from_an_in_memory_source() {
+    let state = app_state(Path::new("/nonexistent/console.log"));
+    let mut rx = state.tx.subscribe();
+    let (source, lines) = MemoryLogSource::new();
+    for line in ["JOIN|1|Alice", "LEAVE|2|Alice"] {
+        lines.send(line.to_string()).unwrap();
+    }
+    drop(lines);
+
+    watch_log(Arc::clone(&state), Arc::clone(&state.servers[0]), source)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        kinds.push(event.event.kind());
+    }
+    assert_eq!(kinds, ["join", "active", "leave", "empty"]);
+}
+
+#[tokio::test]
+async fn leave_of_unknown_player_is_ignored() {
+    let state = app_state(Path::new("/nonexistent/console.log"));
+    let mut rx = state.tx.subscribe();
+    let (source, lines) = MemoryLogSource::new();
+    for line in ["LEAVE|1|Ghost", "JOIN|2|Alice"] {
+        lines.send(line.to_string()).unwrap();
+    }
+    drop(lines);
+
+    watch_log(Arc::clone(&state), Arc::clone(&state.servers[0]), source)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        kinds.push(event.event.kind());
+    }
+    assert_eq!(kinds, ["join", "active"]);
+}
+
+#[tokio::test]
+async fn quick_disconnect_within_join_grace_is_not_announced() {
+    let state = Arc::new(new_app_state(
+        Path::new("/nonexistent/console.log"),
+        Duration::from_secs(60),
+    ));
+    let mut rx = state.tx.subscribe();
+    let (source, lines) = MemoryLogSource::new();
+    for line in ["JOIN|1|Alice", "LEAVE|2|Alice"] {
+        lines.send(line.to_string()).unwrap();
+    }
+    drop(lines);
+
+    watch_log(Arc::clone(&state), Arc::clone(&state.servers[0]), source)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        kinds.push(event.event.kind());
+    }
+    assert!(kinds.is_empty(), "expected no events, got {kinds:?}");
+}
+
+#[tokio::test]
+async fn history_is_replayed_silently() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("console.log");
+    std::fs::write(
+        &log_path,
+        "JOIN|1|Alice\nJOIN|2|Bob\nServer Session Started\nJOIN|3|Carol\nLEAVE|4|Dave\n",
+    )
+    .unwrap();
+
+    let state = app_state(&log_path);
+    let mut rx = state.tx.subscribe();
+    let watcher = start_watcher(&state).await;
+
+    let players: Vec<String> = state.servers[0]
+        .online_players
+        .read()
+        .await
+        .keys()
+        .cloned()
+        .collect();
+    assert_eq!(players, ["Carol"]);
+    assert!(rx.try_recv().is_err(), "history must not emit events");
+
+    let _ = state.shutdown.send(());
+    watcher.await.unwrap();
+}
+
+#[tokio::test]
+async fn notifications_reach_the_notifier() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("console.log");
+    std::fs::write(&log_path, "").unwrap();
+
+    let state = app_state(&log_path);
+    let notifier = MockNotifier::default();
+    let worker = tokio::spawn(notification_worker(
+        state.tx.subscribe(),
+        vec![Box::new(notifier.clone())],
+        WorkerOptions {
+            rules: Arc::new(std::sync::RwLock::new(Arc::new(NotifyRules {
+                templates: MessageTemplates::with_language(&Settings::default(), "en"),
+                filter: EventFilter {
+                    players: PlayerFilter::new(&[], &[]),
+                    notify_on: NotifyToggles::default(),
+                    chat_keywords: Vec::new(),
+                },
+                aliases: PlayerAliases::new(&[]),
+            }))),
+            prefix_server: false,
+            limiter: None,
+            join_batch_window: Duration::ZERO,
+            reconnect_grace: Duration::ZERO,
+            delivery_concurrency: 0,
+            digest_interval: Duration::ZERO,
+            display_timezone: Tz::UTC,
+            queue: None,
+        },
+        state.shutdown.subscribe(),
+    ));
+    let watcher = start_watcher(&state).await;
+
+    append(&log_path, &["JOIN|1|Alice", "LEAVE|2|Alice"]);
+    timeout(EVENT_TIMEOUT, async {
+        while notifier.messages.lock().unwrap().len() < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("notifications never arrived");
+
+    let _ = state.shutdown.send(());
+    watcher.await.unwrap();
+    worker.await.unwrap();
+
+    assert_eq!(
+        *notifier.messages.lock().unwrap(),
+        [
+            "<b>Alice</b> joined the game".to_string(),
+            "<b>Alice</b> left the game".to_string(),
+        ]
+    );
+}
@@ -0,0 +1,39 @@
+use std::io;
+
+use thiserror::Error;
+
+/// Why following a log stopped. Lets the watcher tell a log that isn't
+/// there yet apart from one it will never be allowed to read.
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("{path} does not exist")]
+    NotFound { path: String },
+    #[error("permission denied reading {path}")]
+    PermissionDenied { path: String },
+    #[error("{path} is a directory, not a file")]
+    NotAFile { path: String },
+    #[error("{path} is not valid UTF-8: {source}")]
+    Parse { path: String, source: io::Error },
+    #[error("file watcher failed: {0}")]
+    Linemux(io::Error),
+    #[error("failed to read {path}: {source}")]
+    Io { path: String, source: io::Error },
+}
+
+impl WatchError {
+    pub fn io(path: &str, source: io::Error) -> Self {
+        let path = path.to_string();
+        match source.kind() {
+            io::ErrorKind::NotFound => Self::NotFound { path },
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            io::ErrorKind::InvalidData => Self::Parse { path, source },
+            _ => Self::Io { path, source },
+        }
+    }
+
+    /// Whether waiting could fix it. A missing file may still be created,
+    /// but wrong permissions or a directory path need someone to step in.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Self::PermissionDenied { .. } | Self::NotAFile { .. })
+    }
+}